@@ -179,7 +179,10 @@
 
         petDOM = "<div class='col sqs-col-4 span-4' style='opacity: 0; transform: translateY(10px); transition: opacity 0.3s ease, transform 0.3s ease;'>";
         petDOM += "<div class='sqs-block image-block html-block'>";
-        petDOM += "<div class='petfinder__img-wrapper' style='aspect-ratio: 4/3; overflow: hidden;'>";
+        // Show the photo's color and blurry placeholder until the photo loads
+        let placeholder = pet.photoDominantColor ? " background-color: " + pet.photoDominantColor + ";" : "";
+        placeholder += pet.photoLqip ? " background-image: url(" + pet.photoLqip + "); background-size: cover;" : "";
+        petDOM += "<div class='petfinder__img-wrapper' style='aspect-ratio: 4/3; overflow: hidden;" + placeholder + "'>";
         petDOM += "<img alt='" + pet.name + "' src='" + pet.photoUrl + "' style='width: 100%; height: 100%; object-fit: cover; transition: transform 0.2s ease;' onmouseover=\"this.style.transform='scale(1.03)'\" onmouseout=\"this.style.transform='scale(1)'\" />";
         petDOM += "</div>";
         petDOM += "<h3>" + pet.name + "</h3>";
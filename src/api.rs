@@ -1,106 +1,1358 @@
-use anyhow::Result;
-use futures::future::join_all;
-use reqwest::Client;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future::{join_all, try_join_all};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::cache::{CacheEntry, CacheLookup, ResponseCache};
+use crate::cloudinary::CloudinaryUrl;
+use crate::error::{ApiError, BuildError};
+use crate::fixtures::{FixtureMode, Fixtures};
+use crate::image_store::{ImageRecord, ImageStore};
+use crate::limits::{ConcurrencyLimits, Host};
 use crate::models::{
-    build_cloudinary_info_url, build_cloudinary_original_url, AdoptapetPet, AdoptapetResponse,
-    CloudinaryInfoResponse, PetDetails, PetDetailsResponse, PhotoMetadata,
+    build_cloudinary_info_url, build_cloudinary_original_url, build_cloudinary_preview_url,
+    build_cloudinary_source_url, AdoptapetPet, AdoptapetResponse, CloudinaryInfoResponse, Pet,
+    PetDetails, PetDetailsResponse, PhotoMetadata, MEDIA_BASE_URL,
 };
+use crate::preview::{analyze_preview, lqip, Preview};
+use crate::probe::probe_dimensions;
+use crate::redact::ApiKey;
+use crate::retry::RetryPolicy;
+use crate::source::{Listing, PetSource};
+
+/// Default Adoptapet search API base URL.
+pub const DEFAULT_BASE_URL: &str = "https://api.adoptapet.com/search";
+
+/// Default number of pets requested per pets_at_shelter page.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Default time allowed to establish a connection, in seconds.
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// Default time allowed between reads of a response, in seconds.
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 30;
+/// Default time allowed for a whole request, in seconds.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
 
-const BASE_URL: &str = "https://api.adoptapet.com/search";
+/// Default User-Agent sent with every request.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Result type for the API layer.
+pub type Result<T> = std::result::Result<T, ApiError>;
 
 /// Client for the Adoptapet API.
 pub struct AdoptapetApi {
     client: Client,
-    api_key: String,
+    api_key: ApiKey,
+    base_url: String,
+    media_base_url: String,
+    page_size: usize,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    cache: Option<ResponseCache>,
+    fixtures: Option<Fixtures>,
+    image_store: Option<Arc<ImageStore>>,
+    srcset_widths: Vec<u32>,
 }
 
-impl AdoptapetApi {
-    /// Create a new API client.
-    pub fn new(api_key: String) -> Self {
-        let client = Client::builder()
-            .build()
-            .expect("Failed to create HTTP client");
+/// Builder for [`AdoptapetApi`].
+pub struct AdoptapetApiBuilder {
+    api_key: ApiKey,
+    base_url: String,
+    media_base_url: String,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    timeout: Duration,
+    user_agent: String,
+    client: Option<Client>,
+    page_size: usize,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    cache: Option<ResponseCache>,
+    fixtures: Option<Fixtures>,
+    image_store: Option<Arc<ImageStore>>,
+    srcset_widths: Vec<u32>,
+}
 
-        Self { client, api_key }
+impl AdoptapetApi {
+    /// Start building an API client.
+    pub fn builder() -> AdoptapetApiBuilder {
+        AdoptapetApiBuilder {
+            api_key: ApiKey::default(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            media_base_url: MEDIA_BASE_URL.to_string(),
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            read_timeout: Duration::from_secs(DEFAULT_READ_TIMEOUT_SECS),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            client: None,
+            page_size: DEFAULT_PAGE_SIZE,
+            retry: RetryPolicy::default(),
+            limits: ConcurrencyLimits::default(),
+            cache: None,
+            fixtures: None,
+            image_store: None,
+            srcset_widths: Vec::new(),
+        }
     }
 
-    /// Fetch all pets at a shelter.
+    /// Fetch all pets at a shelter, paging through the listing until it runs out.
     pub async fn get_pets_at_shelter(&self, shelter_id: &str) -> Result<Vec<AdoptapetPet>> {
+        let mut pets = Vec::new();
+        let mut seen = HashSet::new();
+        let mut start = 1;
+
+        loop {
+            let end = start + self.page_size - 1;
+            let page = self.get_pets_page(shelter_id, start, end).await?;
+
+            match merge_page(&mut pets, &mut seen, page, self.page_size) {
+                PageOutcome::More => {}
+                PageOutcome::Done => break,
+                PageOutcome::Overlap { duplicates } => {
+                    eprintln!(
+                        "Warning: pets_at_shelter page {}-{} repeated {} pets already seen",
+                        start, end, duplicates
+                    );
+                }
+                PageOutcome::Stalled => {
+                    eprintln!(
+                        "Warning: pets_at_shelter page {}-{} returned no new pets, stopping",
+                        start, end
+                    );
+                    break;
+                }
+                PageOutcome::Oversized { received } => {
+                    eprintln!(
+                        "Warning: pets_at_shelter page {}-{} returned {} pets, expected at most {}",
+                        start, end, received, self.page_size
+                    );
+                }
+            }
+
+            start = end + 1;
+        }
+
+        for pet in &mut pets {
+            pet.shelter_id = Some(shelter_id.to_string());
+        }
+
+        Ok(pets)
+    }
+
+    /// Fetch a single start_number/end_number window of the shelter listing.
+    /// Error payloads are errors, never an empty page. Only pages past the
+    /// first may leave out `pets`; a first page without it is an error body
+    /// in a shape we don't recognize.
+    async fn get_pets_page(
+        &self,
+        shelter_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<AdoptapetPet>> {
+        let first_page = start == 1;
+        let start = start.to_string();
+        let end = end.to_string();
         let response: AdoptapetResponse = self
-            .client
-            .get(format!("{}/pets_at_shelter", BASE_URL))
-            .query(&[
-                ("key", self.api_key.as_str()),
-                ("shelter_id", shelter_id),
-                ("start_number", "1"),
-                ("end_number", "500"),
-                ("output", "json"),
-            ])
-            .send()
-            .await?
-            .json()
+            .get_json(Host::Api, "pets_at_shelter", || {
+                self.client
+                    .get(format!("{}/pets_at_shelter", self.base_url))
+                    .query(&[
+                        ("key", self.api_key.expose()),
+                        ("shelter_id", shelter_id),
+                        ("start_number", &start),
+                        ("end_number", &end),
+                        ("output", "json"),
+                    ])
+            })
             .await?;
 
-        Ok(response.pets)
+        if let Some(message) = response.error_message() {
+            return Err(ApiError::Adoptapet {
+                endpoint: "pets_at_shelter".to_string(),
+                message,
+            });
+        }
+        match response.pets {
+            Some(pets) => Ok(pets),
+            None if first_page => Err(ApiError::missing(
+                "pets_at_shelter",
+                format!("pets for shelter {}", shelter_id),
+            )),
+            None => Ok(Vec::new()),
+        }
     }
 
     /// Fetch details for a specific pet.
-    pub async fn get_pet_details(&self, pet_id: &str) -> Option<PetDetails> {
+    /// A response without a `pet` object is reported as missing data.
+    pub async fn get_pet_details(&self, pet_id: &str) -> Result<PetDetails> {
         let response: PetDetailsResponse = self
-            .client
-            .get(format!("{}/pet_details", BASE_URL))
-            .query(&[
-                ("key", self.api_key.as_str()),
-                ("pet_id", pet_id),
-                ("output", "json"),
-            ])
-            .send()
-            .await
-            .ok()?
-            .json()
-            .await
-            .ok()?;
+            .get_json(Host::Api, "pet_details", || {
+                self.client
+                    .get(format!("{}/pet_details", self.base_url))
+                    .query(&[
+                        ("key", self.api_key.expose()),
+                        ("pet_id", pet_id),
+                        ("output", "json"),
+                    ])
+            })
+            .await?;
 
-        response.pet
+        response
+            .pet
+            .ok_or_else(|| ApiError::missing("pet_details", "pet"))
     }
 
     /// Fetch image metadata from Cloudinary using fl_getinfo.
     /// Returns PhotoMetadata with original dimensions and aspect ratio.
-    pub async fn get_image_metadata(&self, original_url: &str) -> Option<PhotoMetadata> {
-        let info_url = build_cloudinary_info_url(original_url)?;
-        let original_url = build_cloudinary_original_url(original_url)?;
-
-        let response: CloudinaryInfoResponse = self
-            .client
-            .get(&info_url)
-            .send()
-            .await
-            .ok()?
-            .json()
+    /// Images already in the image store are answered without a request, except
+    /// when recording or replaying, which need every request to go through.
+    pub async fn get_image_metadata(&self, original_url: &str) -> Result<PhotoMetadata> {
+        let image = CloudinaryUrl::parse_on(original_url, &self.media_base_url)
+            .map_err(|e| ApiError::missing("fl_getinfo", format!("a usable image URL ({})", e)))?;
+        let info_url = build_cloudinary_info_url(&image, &self.media_base_url);
+        let original_url = build_cloudinary_original_url(&image, &self.media_base_url);
+
+        let image_store = self
+            .image_store
+            .as_ref()
+            .filter(|_| self.fixtures.is_none());
+        let stored = image_store.and_then(|s| s.get(&image.image_id()));
+        let mut record = match stored.clone() {
+            Some(record) => record,
+            None => {
+                let info: Result<CloudinaryInfoResponse> = self
+                    .get_json(Host::Media, "fl_getinfo", || self.client.get(&info_url))
+                    .await;
+                let (width, height) = match info {
+                    Ok(response) => (response.input.width, response.input.height),
+                    Err(_) => self.probe_image(&image).await?,
+                };
+                ImageRecord {
+                    width,
+                    height,
+                    ..Default::default()
+                }
+            }
+        };
+
+        if record.needs_preview() {
+            if let Some(preview) = self.get_preview(&image).await {
+                record.blurhash = Some(preview.blurhash);
+                record.palette = preview.palette;
+                record.phash = Some(preview.phash);
+            }
+        }
+
+        if let Some(store) = image_store.filter(|_| stored != Some(record.clone())) {
+            store.insert(&image.image_id(), record.clone());
+        }
+
+        Ok(PhotoMetadata {
+            lqip: record.blurhash.as_deref().and_then(lqip),
+            blurhash: record.blurhash,
+            dominant_color: record.palette.first().cloned(),
+            palette: record.palette,
+            phash: record.phash,
+            ..PhotoMetadata::new(original_url, record.width, record.height)
+        }
+        .with_cloudinary_renditions(&image, &self.srcset_widths, &self.media_base_url))
+    }
+
+    /// Analyze a tiny rendition of the image. Placeholders and hashes are a
+    /// nicety, so failures are logged and the photo is kept without them.
+    async fn get_preview(&self, image: &CloudinaryUrl) -> Option<Preview> {
+        let preview_url = build_cloudinary_preview_url(image, &self.media_base_url);
+        let result = match self.get_bytes("image_preview", &preview_url).await {
+            Ok(bytes) => analyze_preview(&bytes).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        result
+            .map_err(|e| eprintln!("Warning: no preview for image {}: {}", image.image_id(), e))
+            .ok()
+    }
+
+    /// Download a binary response (with retries, within the media concurrency limit).
+    /// In record/replay mode the exchange is saved to or loaded from the fixtures directory.
+    async fn get_bytes(&self, endpoint: &str, url: &str) -> Result<Vec<u8>> {
+        let download = || self.download(endpoint, url);
+        let (status, bytes) = match &self.fixtures {
+            Some(fixtures) => fixtures.bytes(endpoint, url, download).await?,
+            None => download().await?,
+        };
+        if !status.is_success() {
+            return Err(ApiError::http_status(endpoint, status, ""));
+        }
+        Ok(bytes)
+    }
+
+    /// Download a whole binary response over the network, whatever its status.
+    async fn download(&self, endpoint: &str, url: &str) -> Result<(StatusCode, Vec<u8>)> {
+        let _permit = self.limits.acquire(Host::Media).await;
+        let response = self
+            .retry
+            .send(|| self.client.get(url))
             .await
-            .ok()?;
+            .map_err(|e| ApiError::transport(endpoint, e))?;
 
-        let width = response.input.width;
-        let height = response.input.height;
-        let aspect_ratio = width as f32 / height as f32;
+        let status = response.status();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ApiError::transport(endpoint, e))?;
+        Ok((status, bytes.to_vec()))
+    }
 
-        Some(PhotoMetadata {
-            original_url,
-            width,
-            height,
-            aspect_ratio,
-        })
+    /// Fallback for when fl_getinfo fails: read the dimensions from the start of
+    /// the image itself, so the photo is only dropped if the image is unreachable.
+    async fn probe_image(&self, image: &CloudinaryUrl) -> Result<(u32, u32)> {
+        let image_url = build_cloudinary_source_url(image, &self.media_base_url);
+        let _permit = self.limits.acquire(Host::Media).await;
+        probe_dimensions(
+            &self.client,
+            &self.retry,
+            &image_url,
+            self.fixtures.as_ref(),
+        )
+        .await
     }
 
-    /// Fetch image metadata for multiple URLs in parallel.
-    /// Returns a Vec of PhotoMetadata for images that were successfully fetched.
-    pub async fn get_all_image_metadata(&self, original_urls: Vec<&str>) -> Vec<PhotoMetadata> {
+    /// Fetch image metadata for multiple URLs in parallel, bounded by the media concurrency limit.
+    /// Returns one result per URL, in the same order.
+    pub async fn get_all_image_metadata(
+        &self,
+        original_urls: Vec<&str>,
+    ) -> Vec<Result<PhotoMetadata>> {
         let futures: Vec<_> = original_urls
             .into_iter()
             .map(|url| self.get_image_metadata(url))
             .collect();
 
-        join_all(futures).await.into_iter().flatten().collect()
+        join_all(futures).await
+    }
+
+    /// Send a request (with retries, within the host's concurrency limit) and decode its JSON body.
+    async fn get_json<T, F>(&self, host: Host, endpoint: &str, build: F) -> Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn() -> RequestBuilder,
+    {
+        let body = match &self.cache {
+            Some(cache) => self.fetch_cached(cache, host, endpoint, build).await?,
+            None => self.fetch(host, endpoint, build).await?.2,
+        };
+
+        serde_json::from_str(&body).map_err(|e| ApiError::decode(endpoint, &body, e))
+    }
+
+    /// Fetch a response's status, headers and body, failing on anything but 2xx or 304.
+    /// In record/replay mode the exchange is saved to or loaded from the fixtures directory.
+    async fn fetch<F>(
+        &self,
+        host: Host,
+        endpoint: &str,
+        build: F,
+    ) -> Result<(StatusCode, HeaderMap, String)>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let (status, headers, body) = match &self.fixtures {
+            Some(fixtures) => {
+                let url = build()
+                    .build()
+                    .map_err(|e| ApiError::transport(endpoint, e))?
+                    .url()
+                    .clone();
+                match fixtures.mode() {
+                    FixtureMode::Replay => fixtures.replay(endpoint, &url)?,
+                    FixtureMode::Record => {
+                        let (status, headers, body) = self.send(host, endpoint, build).await?;
+                        fixtures.record(endpoint, &url, status, &headers, &body);
+                        (status, headers, body)
+                    }
+                }
+            }
+            None => self.send(host, endpoint, build).await?,
+        };
+
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            return Err(ApiError::http_status(endpoint, status, &body));
+        }
+
+        Ok((status, headers, body))
+    }
+
+    /// Send a request over the network and read the whole response.
+    async fn send<F>(
+        &self,
+        host: Host,
+        endpoint: &str,
+        build: F,
+    ) -> Result<(StatusCode, HeaderMap, String)>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let _permit = self.limits.acquire(host).await;
+
+        let response = self
+            .retry
+            .send(build)
+            .await
+            .map_err(|e| ApiError::transport(endpoint, e))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ApiError::transport(endpoint, e))?;
+
+        Ok((status, headers, body))
+    }
+
+    /// Fetch a response body through the on-disk cache: fresh entries skip the network,
+    /// stale ones are revalidated with If-None-Match / If-Modified-Since.
+    async fn fetch_cached<F>(
+        &self,
+        cache: &ResponseCache,
+        host: Host,
+        endpoint: &str,
+        build: F,
+    ) -> Result<String>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let url = build()
+            .build()
+            .map_err(|e| ApiError::transport(endpoint, e))?
+            .url()
+            .clone();
+
+        let stale = match cache.lookup(endpoint, &url) {
+            CacheLookup::Fresh(entry) => return Ok(entry.body),
+            CacheLookup::Stale(entry) => Some(entry),
+            CacheLookup::Miss => None,
+        };
+        let validators = stale
+            .as_ref()
+            .map(CacheEntry::validators)
+            .unwrap_or_default();
+
+        let (status, headers, body) = self
+            .fetch(host, endpoint, || build().headers(validators.clone()))
+            .await?;
+
+        let (headers, body) = match stale {
+            Some(entry) if status == StatusCode::NOT_MODIFIED => {
+                return Ok(cache.refresh(endpoint, &url, entry).body);
+            }
+            // Nothing stored to fall back on; ask again without validators
+            None if status == StatusCode::NOT_MODIFIED => {
+                let (status, headers, body) = self.fetch(host, endpoint, &build).await?;
+                if status == StatusCode::NOT_MODIFIED {
+                    return Err(ApiError::http_status(endpoint, status, &body));
+                }
+                (headers, body)
+            }
+            _ => (headers, body),
+        };
+
+        // Only cache JSON bodies, and never an error payload: the next run
+        // must ask again rather than replay the error. A listing page without
+        // `pets` may be an error we don't recognize, so it isn't cached either.
+        let is_error = serde_json::from_str::<AdoptapetResponse>(&body).map_or(true, |response| {
+            response.error_message().is_some()
+                || (endpoint == "pets_at_shelter" && response.pets.is_none())
+        });
+        if !is_error {
+            cache.store(endpoint, &url, &headers, &body);
+        }
+        Ok(body)
+    }
+}
+
+impl AdoptapetApiBuilder {
+    /// Adoptapet API key sent as the `key` query parameter.
+    pub fn api_key(mut self, api_key: impl Into<ApiKey>) -> Self {
+        self.api_key = api_key.into();
+        self
+    }
+
+    /// Base URL of the Adoptapet search API (pets_at_shelter, pet_details).
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Base URL of the Cloudinary host probed with fl_getinfo.
+    pub fn media_base_url(mut self, media_base_url: impl Into<String>) -> Self {
+        self.media_base_url = media_base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Time allowed to establish a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Time allowed between reads of a response.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Time allowed for a whole request, from connecting to reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// User-Agent sent with every request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// HTTP client to send requests with, e.g. one set up for a mock server.
+    /// Timeouts and user agent set on this builder don't apply to it. The
+    /// binary configures those instead, so only tests inject a client.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Number of pets requested per pets_at_shelter page.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Retry policy applied to every request.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Concurrency limits applied to every request.
+    pub fn concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// On-disk response cache consulted before every request.
+    pub fn cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Record every exchange to, or replay every exchange from, a fixtures directory.
+    pub fn fixtures(mut self, fixtures: Option<Fixtures>) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// Look image dimensions up in, and add them to, a persistent image store.
+    pub fn image_store(mut self, image_store: Option<Arc<ImageStore>>) -> Self {
+        self.image_store = image_store;
+        self
+    }
+
+    /// Widths of the Cloudinary renditions listed in each photo's `srcset`; none by default.
+    pub fn srcset_widths(mut self, widths: Vec<u32>) -> Self {
+        self.srcset_widths = widths;
+        self
+    }
+
+    /// Build the client, creating an HTTP client unless one was supplied.
+    /// An API key is required unless replaying fixtures.
+    pub fn build(self) -> std::result::Result<AdoptapetApi, BuildError> {
+        let replaying = self
+            .fixtures
+            .as_ref()
+            .is_some_and(|f| f.mode() == FixtureMode::Replay);
+        if self.api_key.expose().is_empty() && !replaying {
+            return Err(BuildError::MissingApiKey);
+        }
+        let client = match self.client {
+            Some(client) => client,
+            None => build_http_client(
+                self.connect_timeout,
+                self.read_timeout,
+                self.timeout,
+                &self.user_agent,
+            )?,
+        };
+
+        Ok(AdoptapetApi {
+            client,
+            api_key: self.api_key,
+            base_url: self.base_url,
+            media_base_url: self.media_base_url,
+            page_size: self.page_size,
+            retry: self.retry,
+            limits: self.limits,
+            cache: self.cache,
+            fixtures: self.fixtures,
+            image_store: self.image_store,
+            srcset_widths: self.srcset_widths,
+        })
+    }
+}
+
+/// Build an HTTP client with the given timeouts and user agent.
+pub fn build_http_client(
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    timeout: Duration,
+    user_agent: &str,
+) -> reqwest::Result<Client> {
+    Client::builder()
+        .connect_timeout(connect_timeout)
+        .read_timeout(read_timeout)
+        .timeout(timeout)
+        .user_agent(user_agent)
+        .build()
+}
+
+/// Adoptapet as a [`PetSource`]: one or more shelter accounts behind a single client.
+pub struct AdoptapetSource {
+    api: AdoptapetApi,
+    shelter_ids: Vec<String>,
+}
+
+impl AdoptapetSource {
+    pub fn new(api: AdoptapetApi, shelter_ids: Vec<String>) -> Self {
+        Self { api, shelter_ids }
+    }
+}
+
+impl Listing for AdoptapetPet {
+    fn id(&self) -> &str {
+        &self.pet_id
+    }
+
+    fn name(&self) -> &str {
+        &self.pet_name
+    }
+}
+
+impl PetSource for AdoptapetSource {
+    type Listing = AdoptapetPet;
+    type Details = PetDetails;
+
+    fn name(&self) -> &str {
+        "Adoptapet"
+    }
+
+    /// Fetch every shelter's listing concurrently and merge them.
+    async fn list_pets(&self) -> Result<Vec<AdoptapetPet>> {
+        let listings = try_join_all(
+            self.shelter_ids
+                .iter()
+                .map(|shelter_id| self.api.get_pets_at_shelter(shelter_id)),
+        )
+        .await?;
+        for (shelter_id, listing) in self.shelter_ids.iter().zip(&listings) {
+            println!(
+                "Fetched {} pets from shelter {} listing",
+                listing.len(),
+                shelter_id
+            );
+        }
+
+        let (pets, cross_listed) = merge_listings(listings);
+        if cross_listed > 0 {
+            println!(
+                "Dropped {} pets cross-listed under another shelter",
+                cross_listed
+            );
+        }
+        Ok(pets)
+    }
+
+    async fn fetch_details(&self, listing: &AdoptapetPet) -> Result<PetDetails> {
+        self.api.get_pet_details(&listing.pet_id).await
+    }
+
+    async fn resolve_photos(
+        &self,
+        listing: &AdoptapetPet,
+        details: Option<&PetDetails>,
+    ) -> Vec<Result<PhotoMetadata>> {
+        let original_urls = listing.get_original_image_urls(details);
+        self.api.get_all_image_metadata(original_urls).await
+    }
+
+    fn convert(
+        &self,
+        listing: AdoptapetPet,
+        details: Option<PetDetails>,
+        photos: Vec<PhotoMetadata>,
+    ) -> Pet {
+        listing.into_pet(details.as_ref(), photos, &self.api.media_base_url)
+    }
+}
+
+/// Merge listings from several shelters, keeping the first listing of any pet
+/// cross-listed under more than one shelter. Returns the merged pets and the
+/// number of cross-listed duplicates dropped.
+pub fn merge_listings(listings: Vec<Vec<AdoptapetPet>>) -> (Vec<AdoptapetPet>, usize) {
+    let mut pets = Vec::new();
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+
+    for pet in listings.into_iter().flatten() {
+        if seen.insert(pet.pet_id.clone()) {
+            pets.push(pet);
+        } else {
+            duplicates += 1;
+        }
+    }
+
+    (pets, duplicates)
+}
+
+/// What a listing page tells us about whether to keep paging.
+#[derive(Debug, PartialEq)]
+enum PageOutcome {
+    /// A full page of new pets; there may be more.
+    More,
+    /// A short or empty page; the listing is exhausted.
+    Done,
+    /// A full page that repeated pets from earlier pages.
+    Overlap { duplicates: usize },
+    /// A non-empty page with nothing new; paging further would loop forever.
+    Stalled,
+    /// More pets than requested; the API ignored the window.
+    Oversized { received: usize },
+}
+
+/// Append the unseen pets from `page` to `pets`, deduplicating by pet_id.
+fn merge_page(
+    pets: &mut Vec<AdoptapetPet>,
+    seen: &mut HashSet<String>,
+    page: Vec<AdoptapetPet>,
+    page_size: usize,
+) -> PageOutcome {
+    let received = page.len();
+    let mut added = 0;
+    for pet in page {
+        if seen.insert(pet.pet_id.clone()) {
+            pets.push(pet);
+            added += 1;
+        }
+    }
+
+    if received == 0 {
+        PageOutcome::Done
+    } else if added == 0 {
+        PageOutcome::Stalled
+    } else if received > page_size {
+        PageOutcome::Oversized { received }
+    } else if received < page_size {
+        PageOutcome::Done
+    } else if added < received {
+        PageOutcome::Overlap {
+            duplicates: received - added,
+        }
+    } else {
+        PageOutcome::More
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::card_photo_url;
+    use crate::presets::Presets;
+    use crate::probe::tests::png_header;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mock_api(server: &MockServer) -> AdoptapetApi {
+        AdoptapetApi::builder()
+            .api_key("test-key")
+            .base_url(format!("{}/search", server.uri()))
+            .media_base_url(server.uri())
+            .retry_policy(RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn pet(id: &str) -> AdoptapetPet {
+        AdoptapetPet {
+            pet_id: id.to_string(),
+            pet_name: format!("Pet {}", id),
+            species: None,
+            primary_breed: None,
+            secondary_breed: None,
+            age: None,
+            sex: None,
+            size: None,
+            large_results_photo_url: None,
+            shelter_id: None,
+        }
+    }
+
+    fn page(ids: &[&str]) -> Vec<AdoptapetPet> {
+        ids.iter().map(|id| pet(id)).collect()
+    }
+
+    #[test]
+    fn test_merge_page_pages_until_short() {
+        let mut pets = Vec::new();
+        let mut seen = HashSet::new();
+
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, page(&["1", "2"]), 2),
+            PageOutcome::More
+        );
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, page(&["3"]), 2),
+            PageOutcome::Done
+        );
+        assert_eq!(pets.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_page_dedupes_overlap() {
+        let mut pets = Vec::new();
+        let mut seen = HashSet::new();
+
+        merge_page(&mut pets, &mut seen, page(&["1", "2"]), 2);
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, page(&["2", "3"]), 2),
+            PageOutcome::Overlap { duplicates: 1 }
+        );
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, page(&["2", "3"]), 2),
+            PageOutcome::Stalled
+        );
+        let ids: Vec<_> = pets.iter().map(|p| p.pet_id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_merge_page_flags_oversized_page() {
+        let mut pets = Vec::new();
+        let mut seen = HashSet::new();
+
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, page(&["1", "2", "3"]), 2),
+            PageOutcome::Oversized { received: 3 }
+        );
+        assert_eq!(
+            merge_page(&mut pets, &mut seen, Vec::new(), 2),
+            PageOutcome::Done
+        );
+    }
+
+    #[test]
+    fn test_merge_listings_drops_cross_listed_pets() {
+        let mut main = page(&["1", "2"]);
+        let mut foster = page(&["2", "3"]);
+        main.iter_mut()
+            .for_each(|p| p.shelter_id = Some("main".to_string()));
+        foster
+            .iter_mut()
+            .for_each(|p| p.shelter_id = Some("foster".to_string()));
+
+        let (pets, duplicates) = merge_listings(vec![main, foster]);
+        assert_eq!(duplicates, 1);
+        let merged: Vec<_> = pets
+            .iter()
+            .map(|p| (p.pet_id.as_str(), p.shelter_id.as_deref().unwrap()))
+            .collect();
+        assert_eq!(merged, [("1", "main"), ("2", "main"), ("3", "foster")]);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pets_at_shelter"))
+            .and(query_param("key", "test-key"))
+            .and(query_param("start_number", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pets": [{ "pet_id": "1", "pet_name": "Holiday", "species": "dog" }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .and(query_param("pet_id", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pet": {
+                    "images": [{ "original_url": "https://media.adoptapet.com/image/upload/v1/1268757503" }]
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/fl_getinfo/v1/1268757503"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "input": { "width": 750, "height": 1000 } })),
+            )
+            .mount(&server)
+            .await;
+
+        let api = mock_api(&server);
+        let pets = api.get_pets_at_shelter("83349").await.unwrap();
+        assert_eq!(pets.len(), 1);
+
+        let details = api.get_pet_details(&pets[0].pet_id).await.unwrap();
+        let urls = pets[0].get_original_image_urls(Some(&details));
+        let photos = api.get_all_image_metadata(urls).await;
+
+        let photo = photos[0].as_ref().unwrap();
+        assert_eq!((photo.width, photo.height), (750, 1000));
+        assert_eq!(
+            photo.original_url,
+            format!("{}/image/upload/f_auto,q_auto/v1/1268757503", server.uri())
+        );
+
+        // URLs built on the mock media host stay usable further down the pipeline
+        let photos: Vec<PhotoMetadata> = photos.into_iter().map(Result::unwrap).collect();
+        let mut pet = pets[0]
+            .clone()
+            .into_pet(Some(&details), photos, &server.uri());
+        let card = format!(
+            "{}/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/v1/1268757503",
+            server.uri()
+        );
+        assert_eq!(pet.photo_url.as_deref(), Some(card.as_str()));
+        assert_eq!(
+            card_photo_url(&pet.photos[0].original_url, &server.uri()),
+            card
+        );
+        assert!(pet.photos[0].is_shown_by(&card, &server.uri()));
+
+        let presets = Presets {
+            media_base_url: server.uri(),
+            ..Default::default()
+        };
+        presets.apply(&mut pet, false);
+        assert!(pet
+            .thumbnail_url
+            .as_deref()
+            .is_some_and(|url| url.starts_with(&server.uri())));
+    }
+
+    #[tokio::test]
+    async fn test_http_status_is_reported_as_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("not found"))
+            .mount(&server)
+            .await;
+
+        let error = mock_api(&server).get_pet_details("1").await.unwrap_err();
+        assert!(matches!(
+            error,
+            ApiError::HttpStatus { status, .. } if status == reqwest::StatusCode::NOT_FOUND
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_image_store_skips_known_images_and_keeps_previews() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/fl_getinfo/2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "input": { "width": 640, "height": 480 } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let mut preview = Vec::new();
+        image::DynamicImage::new_rgb8(32, 24)
+            .write_to(
+                &mut std::io::Cursor::new(&mut preview),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        Mock::given(method("GET"))
+            .and(path("/image/upload/c_limit,w_32,h_32/f_jpg,q_60/2"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(preview.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/fl_getinfo/v1800/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "input": { "width": 1200, "height": 900 } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/c_limit,w_32,h_32/f_jpg,q_60/v1800/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(preview))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir =
+            std::env::temp_dir().join(format!("update-pets-api-images-{}", std::process::id()));
+        let store = Arc::new(ImageStore::load(dir.join("pets.images.json")));
+        store.insert(
+            "v1712/1",
+            ImageRecord {
+                width: 750,
+                height: 1000,
+                blurhash: Some("L00000fQfQfQfQfQfQfQfQfQfQfQ".to_string()),
+                palette: vec!["#000000".to_string()],
+                phash: Some("0000000000000000".to_string()),
+            },
+        );
+        let api = AdoptapetApi::builder()
+            .api_key("test-key")
+            .media_base_url(server.uri())
+            .image_store(Some(store.clone()))
+            .build()
+            .unwrap();
+
+        let known = api
+            .get_image_metadata("https://media.adoptapet.com/image/upload/v1712/1")
+            .await
+            .unwrap();
+        // Records are keyed by version and public ID, so a re-upload is looked up afresh
+        let reuploaded = api
+            .get_image_metadata("https://media.adoptapet.com/image/upload/v1800/1")
+            .await
+            .unwrap();
+        let new = api
+            .get_image_metadata("https://media.adoptapet.com/image/upload/2")
+            .await
+            .unwrap();
+
+        assert_eq!((known.width, known.height), (750, 1000));
+        assert_eq!(
+            known.blurhash.as_deref(),
+            Some("L00000fQfQfQfQfQfQfQfQfQfQfQ")
+        );
+        assert_eq!((reuploaded.width, reuploaded.height), (1200, 900));
+        assert_eq!(store.get("v1712/1").unwrap().width, 750);
+        assert_eq!(store.get("v1800/1").unwrap().width, 1200);
+        assert_eq!((new.width, new.height), (640, 480));
+        let record = store.get("2").unwrap();
+        assert_eq!((record.width, record.height), (640, 480));
+        assert_eq!(record.blurhash, new.blurhash);
+        assert_eq!(new.dominant_color.as_deref(), Some("#000000"));
+        assert!(new.lqip.unwrap().starts_with("data:image/jpeg;base64,"));
+    }
+
+    #[tokio::test]
+    async fn test_image_metadata_falls_back_to_probing() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/fl_getinfo/1"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/fl_getinfo/2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>oops</html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/1"))
+            .and(wiremock::matchers::header_exists("Range"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(png_header(750, 1000)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/image/upload/2"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let api = mock_api(&server);
+        let probed = api
+            .get_image_metadata("https://media.adoptapet.com/image/upload/1")
+            .await
+            .unwrap();
+        assert_eq!((probed.width, probed.height), (750, 1000));
+        assert_eq!(
+            probed.original_url,
+            format!("{}/image/upload/f_auto,q_auto/1", server.uri())
+        );
+
+        let error = api
+            .get_image_metadata("https://media.adoptapet.com/image/upload/2")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), "http_status");
+    }
+
+    #[tokio::test]
+    async fn test_cache_revalidates_with_etag() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .and(wiremock::matchers::header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_json(json!({ "pet": { "color": "Brown" } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir =
+            std::env::temp_dir().join(format!("update-pets-api-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let ttls = crate::cache::CacheTtls {
+            details: Duration::ZERO,
+            ..Default::default()
+        };
+        let api = AdoptapetApi::builder()
+            .api_key("test-key")
+            .base_url(format!("{}/search", server.uri()))
+            .cache(Some(ResponseCache::new(&dir, ttls)))
+            .build()
+            .unwrap();
+
+        let first = api.get_pet_details("1").await.unwrap();
+        let second = api.get_pet_details("1").await.unwrap();
+        assert_eq!(first.color.as_deref(), Some("Brown"));
+        assert_eq!(second.color.as_deref(), Some("Brown"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_skips_error_payloads_and_bare_not_modified() {
+        let server = MockServer::start().await;
+        // A 304 to a request without validators, then an error payload
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(ResponseTemplate::new(304))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "error": "Invalid API key" })),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "pet": { "color": "Brown" } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = std::env::temp_dir().join(format!(
+            "update-pets-api-cache-errors-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let api = AdoptapetApi::builder()
+            .api_key("test-key")
+            .base_url(format!("{}/search", server.uri()))
+            .cache(Some(ResponseCache::new(&dir, Default::default())))
+            .build()
+            .unwrap();
+
+        // The 304 is retried and the error payload isn't cached...
+        assert!(api.get_pet_details("1").await.is_err());
+        // ...so this asks again, and the good answer is then served from the cache
+        let details = api.get_pet_details("1").await.unwrap();
+        assert_eq!(details.color.as_deref(), Some("Brown"));
+        let cached = api.get_pet_details("1").await.unwrap();
+        assert_eq!(cached.color.as_deref(), Some("Brown"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_build_uses_an_injected_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .and(wiremock::matchers::header("X-Test-Client", "injected"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "pet": { "color": "Brown" } })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let headers = HeaderMap::from_iter([(
+            reqwest::header::HeaderName::from_static("x-test-client"),
+            reqwest::header::HeaderValue::from_static("injected"),
+        )]);
+        let client = Client::builder().default_headers(headers).build().unwrap();
+        let api = AdoptapetApi::builder()
+            .api_key("test-key")
+            .base_url(format!("{}/search", server.uri()))
+            .client(client)
+            .build()
+            .unwrap();
+
+        let details = api.get_pet_details("1").await.unwrap();
+        assert_eq!(details.color.as_deref(), Some("Brown"));
+    }
+
+    #[test]
+    fn test_build_requires_an_api_key_unless_replaying() {
+        assert!(matches!(
+            AdoptapetApi::builder().build(),
+            Err(BuildError::MissingApiKey)
+        ));
+
+        let dir = std::env::temp_dir().join("update-pets-api-no-key");
+        let replayer = AdoptapetApi::builder()
+            .fixtures(Some(Fixtures::new(&dir, FixtureMode::Replay)))
+            .build();
+        assert!(replayer.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_without_network() {
+        let dir = std::env::temp_dir().join(format!("update-pets-fixtures-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pet_details"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "pet": { "color": "Black" } })),
+            )
+            .mount(&server)
+            .await;
+        let base_url = format!("{}/search", server.uri());
+
+        let recorder = AdoptapetApi::builder()
+            .api_key("secret-key")
+            .base_url(&base_url)
+            .fixtures(Some(Fixtures::new(&dir, FixtureMode::Record)))
+            .build()
+            .unwrap();
+        recorder.get_pet_details("7").await.unwrap();
+        drop(server);
+
+        let recorded = std::fs::read_to_string(dir.join("pet_details/pet_id-7.json")).unwrap();
+        assert!(!recorded.contains("secret-key"));
+
+        let replayer = AdoptapetApi::builder()
+            .api_key("other-key")
+            .base_url(&base_url)
+            .fixtures(Some(Fixtures::new(&dir, FixtureMode::Replay)))
+            .build()
+            .unwrap();
+        let details = replayer.get_pet_details("7").await.unwrap();
+        assert_eq!(details.color.as_deref(), Some("Black"));
+        assert!(matches!(
+            replayer.get_pet_details("8").await,
+            Err(ApiError::NotRecorded { .. })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_never_contains_api_key() {
+        // Grab a free port and close it so the connection is refused
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let api = AdoptapetApi::builder()
+            .api_key("super-secret-key")
+            .base_url(format!("http://127.0.0.1:{}/search", port))
+            .retry_policy(RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            })
+            .build()
+            .unwrap();
+
+        let error = api.get_pets_at_shelter("83349").await.unwrap_err();
+        assert!(matches!(error, ApiError::Transport { .. }));
+
+        let error = anyhow::Error::from(error);
+        for rendered in [
+            format!("{}", error),
+            format!("{:#}", error),
+            format!("{:?}", error),
+        ] {
+            assert!(!rendered.contains("super-secret-key"), "{}", rendered);
+        }
+        assert!(format!("{:?}", error).contains("key=%5BREDACTED%5D"));
+    }
+
+    #[tokio::test]
+    async fn test_listing_error_payload_is_not_an_empty_shelter() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pets_at_shelter"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "error": "Invalid API key" })),
+            )
+            .mount(&server)
+            .await;
+
+        let error = mock_api(&server)
+            .get_pets_at_shelter("83349")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pets_at_shelter returned an error: Invalid API key"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_one_failing_shelter_fails_the_listing() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/pets_at_shelter"))
+            .and(query_param("shelter_id", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pets": [{ "pet_id": "10", "pet_name": "Holiday", "species": "dog" }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/pets_at_shelter"))
+            .and(query_param("shelter_id", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "errors": [{ "code": 403, "detail": "Shelter suspended" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let source = AdoptapetSource::new(mock_api(&server), vec!["1".into(), "2".into()]);
+        let error = source.list_pets().await.unwrap_err();
+        assert_eq!(error.kind(), "missing_data");
+        assert!(error.to_string().contains("shelter 2"));
     }
 }
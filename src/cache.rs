@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::redact::strip_api_key;
+
+/// Default freshness of a cached pets_at_shelter page, in hours (always revalidate).
+pub const DEFAULT_LISTING_TTL_HOURS: u64 = 0;
+/// Default freshness of a cached pet_details response, in hours. Shorter than
+/// the 12 hours between scheduled runs, so each run revalidates descriptions.
+pub const DEFAULT_DETAILS_TTL_HOURS: u64 = 6;
+/// Default freshness of a cached fl_getinfo response, in hours.
+/// Dimensions of a Cloudinary image never change, so this is long.
+pub const DEFAULT_IMAGE_INFO_TTL_HOURS: u64 = 24 * 30;
+
+/// How long each endpoint's responses are served from the cache without revalidating.
+#[derive(Debug, Clone)]
+pub struct CacheTtls {
+    pub listing: Duration,
+    pub details: Duration,
+    pub image_info: Duration,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            listing: Duration::from_secs(DEFAULT_LISTING_TTL_HOURS * 3600),
+            details: Duration::from_secs(DEFAULT_DETAILS_TTL_HOURS * 3600),
+            image_info: Duration::from_secs(DEFAULT_IMAGE_INFO_TTL_HOURS * 3600),
+        }
+    }
+}
+
+/// A successful response stored on disk.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Request URL with the API key removed
+    pub url: String,
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Result of looking a request up in the cache.
+pub enum CacheLookup {
+    /// Within its TTL; use without touching the network.
+    Fresh(CacheEntry),
+    /// Past its TTL; revalidate with the entry's validators.
+    Stale(CacheEntry),
+    Miss,
+}
+
+/// On-disk cache of successful API responses, keyed by URL minus the API key.
+/// Failures to read or write the cache are logged and otherwise ignored.
+#[derive(Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttls: CacheTtls,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, ttls: CacheTtls) -> Self {
+        Self {
+            dir: dir.into(),
+            ttls,
+        }
+    }
+
+    /// Look up a cached response for `url` on `endpoint`.
+    pub fn lookup(&self, endpoint: &str, url: &Url) -> CacheLookup {
+        let path = self.entry_path(endpoint, url);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return CacheLookup::Miss;
+        };
+        let entry: CacheEntry = match serde_json::from_str(&contents) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Warning: ignoring corrupt cache entry {:?}: {}", path, e);
+                return CacheLookup::Miss;
+            }
+        };
+
+        let age = (Utc::now() - entry.fetched_at).to_std().unwrap_or_default();
+        if age < self.ttl(endpoint) {
+            CacheLookup::Fresh(entry)
+        } else {
+            CacheLookup::Stale(entry)
+        }
+    }
+
+    /// Store a successful response body along with its validators.
+    pub fn store(&self, endpoint: &str, url: &Url, headers: &HeaderMap, body: &str) {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v: &HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let entry = CacheEntry {
+            url: strip_api_key(url),
+            body: body.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            fetched_at: Utc::now(),
+        };
+        self.write(&self.entry_path(endpoint, url), &entry);
+    }
+
+    /// Mark a stale entry as fresh again after a 304 Not Modified.
+    pub fn refresh(&self, endpoint: &str, url: &Url, mut entry: CacheEntry) -> CacheEntry {
+        entry.fetched_at = Utc::now();
+        self.write(&self.entry_path(endpoint, url), &entry);
+        entry
+    }
+
+    fn ttl(&self, endpoint: &str) -> Duration {
+        match endpoint {
+            "pets_at_shelter" => self.ttls.listing,
+            "pet_details" => self.ttls.details,
+            "fl_getinfo" => self.ttls.image_info,
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn entry_path(&self, endpoint: &str, url: &Url) -> PathBuf {
+        let digest = Sha256::digest(strip_api_key(url).as_bytes());
+        self.dir.join(endpoint).join(format!("{:x}.json", digest))
+    }
+
+    fn write(&self, path: &Path, entry: &CacheEntry) {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, serde_json::to_string(entry).unwrap_or_default()));
+        if let Err(e) = result {
+            eprintln!("Warning: could not write cache entry {:?}: {}", path, e);
+        }
+    }
+}
+
+impl CacheEntry {
+    /// Conditional request headers that revalidate this entry.
+    pub fn validators(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = self.etag.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+        if let Some(value) = self.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("update-pets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_lookup_respects_ttl_and_keeps_validators() {
+        let dir = temp_dir("cache-ttl");
+        let url = Url::parse("https://api.example.com/search/pet_details?key=a&pet_id=1").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+
+        let cache = ResponseCache::new(&dir, CacheTtls::default());
+        assert!(matches!(
+            cache.lookup("pet_details", &url),
+            CacheLookup::Miss
+        ));
+
+        cache.store("pet_details", &url, &headers, "{}");
+        let CacheLookup::Fresh(entry) = cache.lookup("pet_details", &url) else {
+            panic!("expected a fresh entry");
+        };
+        assert_eq!(entry.body, "{}");
+        assert!(!entry.url.contains("key="));
+
+        let stale = ResponseCache::new(
+            &dir,
+            CacheTtls {
+                details: Duration::ZERO,
+                ..CacheTtls::default()
+            },
+        );
+        let CacheLookup::Stale(entry) = stale.lookup("pet_details", &url) else {
+            panic!("expected a stale entry");
+        };
+        assert_eq!(entry.validators().get(IF_NONE_MATCH).unwrap(), "\"abc\"");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,376 @@
+use std::fmt;
+
+use reqwest::Url;
+use thiserror::Error;
+
+use crate::models::MEDIA_BASE_URL;
+
+/// Hosts serving Adoptapet's Cloudinary images, `MEDIA_BASE_URL`'s first.
+/// Their paths start at the resource type, the cloud name being implied by
+/// the host.
+const ADOPTAPET_HOSTS: &[&str] = &["media.adoptapet.com"];
+
+/// Cloudinary's shared delivery host, whose paths start with the cloud name.
+const SHARED_HOST: &str = "res.cloudinary.com";
+
+/// Parameter names that mark a path segment as a transformation ("c_fill,w_800").
+const TRANSFORMATION_PARAMS: &[&str] = &[
+    "a", "ac", "af", "ar", "b", "bo", "br", "c", "co", "cs", "d", "dl", "dn", "dpr", "du", "e",
+    "eo", "f", "fl", "fn", "fps", "g", "h", "if", "ki", "l", "o", "p", "pg", "q", "r", "so", "sp",
+    "t", "u", "vc", "vs", "w", "x", "y", "z",
+];
+
+/// Extensions Cloudinary treats as a delivery format rather than part of the public ID.
+const FORMATS: &[&str] = &[
+    "avif", "bmp", "gif", "heic", "jpeg", "jpg", "jxl", "png", "svg", "tif", "tiff", "webp",
+];
+
+/// Why a URL isn't a usable Adoptapet Cloudinary URL.
+#[derive(Debug, Error, PartialEq)]
+pub enum CloudinaryUrlError {
+    #[error("not a URL: {0:?}")]
+    NotUrl(String),
+
+    /// The host isn't one Adoptapet serves images from; building URLs from it
+    /// would point the site at someone else's images.
+    #[error("{0:?} is not an Adoptapet image host")]
+    ForeignHost(String),
+
+    /// A URL on Cloudinary's shared host, for the named cloud. Adoptapet
+    /// serves its images from its own host only.
+    #[error("{0:?} is not Adoptapet's Cloudinary cloud")]
+    ForeignCloud(String),
+
+    #[error("not a Cloudinary delivery URL: {0:?}")]
+    NotCloudinary(String),
+}
+
+/// An Adoptapet Cloudinary delivery URL, split into its parts:
+///
+/// ```text
+/// https://media.adoptapet.com/image/upload/c_fill,w_800/f_auto,q_auto/v1712/pets/1268757503.jpg
+///                             ^^^^^ ^^^^^^ ^^^^^^^^^^^^^^^^^^^^^^^^^^ ^^^^^ ^^^^^^^^^^^^^^^ ^^^
+///                    resource type  type  transformations         version  public ID  extension
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudinaryUrl {
+    /// "image" for photos
+    pub resource_type: String,
+    /// Delivery type, e.g. "upload" or "fetch"
+    pub delivery_type: String,
+    /// Chained transformations, one per path segment
+    pub transformations: Vec<String>,
+    /// Version number, without the leading "v"
+    pub version: Option<u64>,
+    /// Public ID, which may contain folders and dots
+    pub public_id: String,
+    /// Delivery format, if the URL names one
+    pub extension: Option<String>,
+}
+
+impl CloudinaryUrl {
+    /// Parse a URL on Adoptapet's hosts or the configured media host
+    /// (`--media-base-url`), which the run builds its own URLs on.
+    pub fn parse_on(url: &str, media_base_url: &str) -> Result<Self, CloudinaryUrlError> {
+        let media_base_url = Url::parse(media_base_url).ok();
+        let hosts: Vec<&str> = media_base_url
+            .as_ref()
+            .and_then(Url::host_str)
+            .into_iter()
+            .chain(ADOPTAPET_HOSTS.iter().copied())
+            .collect();
+        Self::parse_with_hosts(url, &hosts)
+    }
+
+    /// Parse a Cloudinary URL served from one of `hosts`, rejecting any other host.
+    pub fn parse_with_hosts(url: &str, hosts: &[&str]) -> Result<Self, CloudinaryUrlError> {
+        let parsed = Url::parse(url.trim()).map_err(|_| CloudinaryUrlError::NotUrl(url.into()))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(CloudinaryUrlError::NotUrl(url.into()));
+        }
+        let mut segments: Vec<&str> = parsed.path_segments().into_iter().flatten().collect();
+        let host = parsed.host_str().unwrap_or_default();
+        if host == SHARED_HOST && segments.len() > 1 {
+            let cloud_name = segments.remove(0);
+            if Self::from_segments(&segments).is_some() {
+                return Err(CloudinaryUrlError::ForeignCloud(cloud_name.to_string()));
+            }
+        }
+        if !hosts.contains(&host) {
+            return Err(CloudinaryUrlError::ForeignHost(host.to_string()));
+        }
+
+        Self::from_segments(&segments)
+            .ok_or_else(|| CloudinaryUrlError::NotCloudinary(url.to_string()))
+    }
+
+    /// Split the path, from the resource type on, into its parts.
+    fn from_segments(segments: &[&str]) -> Option<Self> {
+        let mut segments = segments.iter().copied();
+        let resource_type = segments.next().filter(|s| !s.is_empty())?.to_string();
+        let delivery_type = segments.next().filter(|s| !s.is_empty())?.to_string();
+
+        let rest: Vec<&str> = segments.collect();
+        let mut start = 0;
+        let mut transformations = Vec::new();
+        let mut version = None;
+        // The last segment is always the public ID
+        while start + 1 < rest.len() {
+            if let Some(v) = parse_version(rest[start]) {
+                // Everything after the version is the public ID
+                version = Some(v);
+                start += 1;
+                break;
+            } else if is_transformation(rest[start]) {
+                transformations.push(rest[start].to_string());
+                start += 1;
+            } else {
+                break;
+            }
+        }
+
+        let path = rest.get(start..)?.join("/");
+        let (public_id, extension) = match path.rsplit_once('.') {
+            Some((id, ext)) if FORMATS.contains(&ext.to_ascii_lowercase().as_str()) => {
+                (id.to_string(), Some(ext.to_string()))
+            }
+            _ => (path, None),
+        };
+        if public_id.is_empty() || public_id.split('/').any(str::is_empty) {
+            return None;
+        }
+
+        Some(Self {
+            resource_type,
+            delivery_type,
+            transformations,
+            version,
+            public_id,
+            extension,
+        })
+    }
+
+    /// The same image with a different transformation ("" for none), e.g.
+    /// "c_fill,w_800,h_600/f_auto,q_auto".
+    pub fn with_transformation(&self, transformation: &str) -> Self {
+        Self {
+            transformations: transformation
+                .split('/')
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Identifies the stored image, whatever the transformation:
+    /// the public ID, prefixed with the version when there is one.
+    pub fn image_id(&self) -> String {
+        match self.version {
+            Some(version) => format!("v{}/{}", version, self.public_id),
+            None => self.public_id.clone(),
+        }
+    }
+
+    /// Build the URL on `base_url`, e.g. "https://media.adoptapet.com".
+    pub fn to_url(&self, base_url: &str) -> String {
+        let mut url = format!(
+            "{}/{}/{}/",
+            base_url.trim_end_matches('/'),
+            self.resource_type,
+            self.delivery_type
+        );
+        for transformation in &self.transformations {
+            url.push_str(transformation);
+            url.push('/');
+        }
+        url.push_str(&self.image_id());
+        if let Some(extension) = &self.extension {
+            url.push('.');
+            url.push_str(extension);
+        }
+        url
+    }
+}
+
+impl fmt::Display for CloudinaryUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_url(MEDIA_BASE_URL))
+    }
+}
+
+/// "v1712" -> 1712
+fn parse_version(segment: &str) -> Option<u64> {
+    let digits = segment.strip_prefix('v')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Whether every comma-separated part of a segment is a known parameter, like "w_800".
+pub fn is_transformation(segment: &str) -> bool {
+    segment.split(',').all(|part| {
+        part.split_once('_')
+            .is_some_and(|(name, value)| !value.is_empty() && TRANSFORMATION_PARAMS.contains(&name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn parse(url: &str) -> Result<CloudinaryUrl, CloudinaryUrlError> {
+        CloudinaryUrl::parse_with_hosts(url, ADOPTAPET_HOSTS)
+    }
+
+    #[test]
+    fn test_parse_cloudinary_url() {
+        let url = parse(
+            "https://media.adoptapet.com/image/upload/c_fill,w_800/f_auto,q_auto/v1712/pets/my.dog.JPG",
+        )
+        .unwrap();
+        assert_eq!(
+            url,
+            CloudinaryUrl {
+                resource_type: "image".to_string(),
+                delivery_type: "upload".to_string(),
+                transformations: vec!["c_fill,w_800".to_string(), "f_auto,q_auto".to_string()],
+                version: Some(1712),
+                public_id: "pets/my.dog".to_string(),
+                extension: Some("JPG".to_string()),
+            }
+        );
+        assert_eq!(
+            url.with_transformation("fl_getinfo")
+                .to_url("http://localhost:1234"),
+            "http://localhost:1234/image/upload/fl_getinfo/v1712/pets/my.dog.JPG"
+        );
+
+        // Without a version, folders that don't look like transformations start the ID
+        let url = parse("https://media.adoptapet.com/image/upload/q_auto/my_pets/1").unwrap();
+        assert_eq!(url.transformations, ["q_auto"]);
+        assert_eq!(url.public_id, "my_pets/1");
+    }
+
+    #[test]
+    fn test_media_base_url_is_an_adoptapet_host() {
+        let url = Url::parse(MEDIA_BASE_URL).unwrap();
+        assert_eq!(url.host_str(), Some(ADOPTAPET_HOSTS[0]));
+    }
+
+    #[test]
+    fn test_parse_on_accepts_the_configured_media_host() {
+        let url = "http://127.0.0.1:8080/image/upload/f_auto,q_auto/v1/1268757503";
+        assert_eq!(
+            parse(url),
+            Err(CloudinaryUrlError::ForeignHost("127.0.0.1".to_string()))
+        );
+        let parsed = CloudinaryUrl::parse_on(url, "http://127.0.0.1:8080").unwrap();
+        assert_eq!(parsed.image_id(), "v1/1268757503");
+        assert!(CloudinaryUrl::parse_on(
+            "https://media.adoptapet.com/image/upload/1",
+            "http://127.0.0.1:8080"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_other_hosts_and_shapes() {
+        assert_eq!(
+            parse("https://res.cloudinary.com/demo/image/upload/sample.jpg"),
+            Err(CloudinaryUrlError::ForeignCloud("demo".to_string()))
+        );
+        assert_eq!(
+            parse("https://media.adoptapet.com.evil.example/image/upload/1"),
+            Err(CloudinaryUrlError::ForeignHost(
+                "media.adoptapet.com.evil.example".to_string()
+            ))
+        );
+        assert!(matches!(
+            parse("https://media.adoptapet.com/image/upload/"),
+            Err(CloudinaryUrlError::NotCloudinary(_))
+        ));
+        assert!(matches!(
+            parse("1268757503"),
+            Err(CloudinaryUrlError::NotUrl(_))
+        ));
+    }
+
+    fn transformation() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            (prop::sample::select(TRANSFORMATION_PARAMS), "[a-z0-9]{1,6}"),
+            1..4,
+        )
+        .prop_map(|params| {
+            params
+                .iter()
+                .map(|(name, value)| format!("{}_{}", name, value))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    fn cloudinary_url() -> impl Strategy<Value = CloudinaryUrl> {
+        (
+            prop::collection::vec(transformation(), 0..3),
+            prop::option::of(0u64..10_000_000_000),
+            // Folders and dots, but no segment that could pass for a version
+            // or a transformation
+            "[0-9A-Za-z-]{1,12}(/[0-9A-Z][0-9A-Za-z.-]{0,12}){0,2}",
+            prop::option::of(prop::sample::select(FORMATS)),
+        )
+            .prop_map(
+                |(transformations, version, public_id, extension)| CloudinaryUrl {
+                    resource_type: "image".to_string(),
+                    delivery_type: "upload".to_string(),
+                    transformations,
+                    version,
+                    public_id,
+                    extension: extension.map(str::to_string),
+                },
+            )
+            .prop_filter(
+                "public ID mistaken for a version or transformation",
+                |url| {
+                    let first = url.public_id.split('/').next().unwrap_or_default();
+                    parse_version(first).is_none()
+                        && !is_transformation(first)
+                        && url.public_id.rsplit_once('.').is_none_or(|(_, ext)| {
+                            !FORMATS.contains(&ext.to_ascii_lowercase().as_str())
+                        })
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_round_trips(url in cloudinary_url()) {
+            let text = url.to_string();
+            prop_assert_eq!(parse(&text), Ok(url));
+        }
+
+        #[test]
+        fn prop_rebuilt_urls_keep_the_image(url in cloudinary_url(), preset in transformation()) {
+            let rebuilt = parse(&url.with_transformation(&preset).to_string()).unwrap();
+            prop_assert_eq!(rebuilt.image_id(), url.image_id());
+            prop_assert_eq!(rebuilt.extension, url.extension);
+            prop_assert_eq!(rebuilt.transformations, vec![preset]);
+        }
+
+        #[test]
+        fn prop_other_hosts_are_rejected(host in "[a-z]{1,10}\\.(com|org|net)", path in "[a-z0-9/_.]{0,30}") {
+            let url = format!("https://{}/image/upload/{}", host, path);
+            prop_assert_eq!(
+                parse(&url),
+                Err(CloudinaryUrlError::ForeignHost(host))
+            );
+        }
+
+        #[test]
+        fn prop_parse_never_panics(text in ".*") {
+            let _ = parse(&text);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use image::RgbImage;
+
+/// Number of colors in a photo's palette.
+pub const PALETTE_SIZE: usize = 5;
+
+/// Bits kept per channel when bucketing pixels; 4 bits gives 4096 buckets.
+const BUCKET_BITS: u8 = 4;
+
+/// Palette colors closer than this (squared RGB distance) to a more common one are skipped.
+const MIN_PALETTE_DISTANCE: u32 = 48 * 48;
+
+/// The most common colors of an image, most common first, as "#rrggbb".
+/// The first entry is the dominant color.
+pub fn palette(image: &RgbImage, size: usize) -> Vec<String> {
+    // Bucket similar pixels together, keeping each bucket's sum for an accurate average
+    let mut buckets: HashMap<[u8; 3], (u32, [u32; 3])> = HashMap::new();
+    for pixel in image.pixels() {
+        let key = pixel.0.map(|channel| channel >> (8 - BUCKET_BITS));
+        let (count, sum) = buckets.entry(key).or_default();
+        *count += 1;
+        for (total, channel) in sum.iter_mut().zip(pixel.0) {
+            *total += channel as u32;
+        }
+    }
+
+    let mut buckets: Vec<_> = buckets.into_values().collect();
+    // Most common first; ties broken by color so the result is stable
+    buckets.sort_by_key(|&(count, sum)| (std::cmp::Reverse(count), sum));
+
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    for (count, sum) in buckets {
+        let color = sum.map(|total| ((total + count / 2) / count) as u8);
+        if colors
+            .iter()
+            .all(|picked| distance(picked, &color) >= MIN_PALETTE_DISTANCE)
+        {
+            colors.push(color);
+            if colors.len() == size {
+                break;
+            }
+        }
+    }
+
+    colors
+        .iter()
+        .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+        .collect()
+}
+
+fn distance(a: &[u8; 3], b: &[u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&x, &y)| (x as i32 - y as i32).pow(2) as u32)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_palette_orders_by_coverage() {
+        // Three quarters grass green, one quarter sky blue, a couple of near-green pixels
+        let mut image = RgbImage::from_fn(8, 8, |x, _| {
+            if x < 6 {
+                Rgb([40, 160, 60])
+            } else {
+                Rgb([90, 160, 230])
+            }
+        });
+        image.put_pixel(0, 0, Rgb([44, 162, 58]));
+
+        assert_eq!(palette(&image, PALETTE_SIZE), ["#28a03c", "#5aa0e6"]);
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use crate::models::{Pet, PhotoMetadata};
+use crate::phash::distance;
+
+/// Photos whose perceptual hashes differ in at most this many bits (of 64)
+/// are treated as the same photo.
+pub const DUPLICATE_DISTANCE: u32 = 6;
+
+fn same_photo(a: &PhotoMetadata, b: &PhotoMetadata) -> bool {
+    match (&a.phash, &b.phash) {
+        (Some(a), Some(b)) => distance(a, b).is_some_and(|d| d <= DUPLICATE_DISTANCE),
+        _ => false,
+    }
+}
+
+/// Drop photos that repeat an earlier photo of the same pet. The first copy is
+/// kept, so the primary photo never changes. Returns how many were dropped.
+pub fn collapse_duplicate_photos(pets: &mut [Pet]) -> usize {
+    let mut dropped = 0;
+    for pet in pets {
+        let mut kept: Vec<PhotoMetadata> = Vec::with_capacity(pet.photos.len());
+        for photo in pet.photos.drain(..) {
+            if kept.iter().any(|k| same_photo(k, &photo)) {
+                dropped += 1;
+            } else {
+                kept.push(photo);
+            }
+        }
+        pet.photos = kept;
+    }
+    dropped
+}
+
+/// Photos that appear on more than one pet, usually a litter photo reused on
+/// every sibling.
+#[derive(Debug, Default)]
+pub struct SharedPhotos {
+    /// Groups of pets (as indices, in listing order) that share at least one
+    /// photo, directly or through another pet in the group
+    pub groups: Vec<Vec<usize>>,
+    /// Original URLs of every shared photo
+    pub urls: HashSet<String>,
+}
+
+/// Find the photos shared between pets, comparing every pair of pets once.
+pub fn find_shared_photos(pets: &[Pet]) -> SharedPhotos {
+    let mut urls = HashSet::new();
+    // Union-find over pets; parent[i] == i for the root of each group
+    let mut parent: Vec<usize> = (0..pets.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for (i, pet) in pets.iter().enumerate() {
+        for (j, other) in pets.iter().enumerate().skip(i + 1) {
+            let mut shared = false;
+            for a in &pet.photos {
+                for b in other.photos.iter().filter(|b| same_photo(a, b)) {
+                    urls.insert(a.original_url.clone());
+                    urls.insert(b.original_url.clone());
+                    shared = true;
+                }
+            }
+            if shared {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root: Vec<Option<usize>> = vec![None; pets.len()];
+    for i in 0..pets.len() {
+        let r = root(&mut parent, i);
+        match group_of_root[r] {
+            Some(g) => groups[g].push(i),
+            None => {
+                group_of_root[r] = Some(groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    SharedPhotos { groups, urls }
+}
+
+/// Record every other pet of its group on each pet, as likely littermates.
+pub fn link_littermates(pets: &mut [Pet], groups: &[Vec<usize>]) {
+    for group in groups {
+        let ids: Vec<String> = group.iter().map(|&i| pets[i].id.clone()).collect();
+        for &i in group {
+            pets[i].littermates = ids
+                .iter()
+                .filter(|&id| *id != pets[i].id)
+                .cloned()
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pet(id: &str, hashes: &[&str]) -> Pet {
+        Pet {
+            id: id.to_string(),
+            photos: hashes
+                .iter()
+                .enumerate()
+                .map(|(n, hash)| PhotoMetadata {
+                    phash: Some(hash.to_string()),
+                    ..PhotoMetadata::new(format!("https://example.org/{}/{}", id, n), 800, 600)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collapse_duplicate_photos() {
+        // The second photo is the first one recompressed (two bits off)
+        let mut pets = vec![pet(
+            "1",
+            &["f0f0f0f0f0f0f0f0", "f0f0f0f0f0f0f0f3", "0f0f0f0f0f0f0f0f"],
+        )];
+
+        assert_eq!(collapse_duplicate_photos(&mut pets), 1);
+        let urls: Vec<_> = pets[0]
+            .photos
+            .iter()
+            .map(|p| p.original_url.as_str())
+            .collect();
+        assert_eq!(urls, ["https://example.org/1/0", "https://example.org/1/2"]);
+    }
+
+    #[test]
+    fn test_find_and_link_littermates() {
+        let litter = "ffff0000ffff0000";
+        let mut pets = vec![
+            pet("1", &["0123456789abcdef", litter]),
+            pet("2", &["aaaaaaaaaaaaaaaa"]),
+            pet("3", &["ffff0000ffff0001"]),
+            pet("4", &[litter]),
+        ];
+
+        let shared = find_shared_photos(&pets);
+        assert_eq!(shared.groups, [vec![0, 2, 3]]);
+        let mut urls: Vec<_> = shared.urls.into_iter().collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            [
+                "https://example.org/1/1",
+                "https://example.org/3/0",
+                "https://example.org/4/0"
+            ]
+        );
+
+        link_littermates(&mut pets, &shared.groups);
+        assert_eq!(pets[0].littermates, ["3", "4"]);
+        assert!(pets[1].littermates.is_empty());
+        assert_eq!(pets[3].littermates, ["1", "3"]);
+    }
+}
@@ -0,0 +1,158 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::redact::redact_error;
+
+/// Maximum number of characters of a response body kept in an error.
+const SNIPPET_LEN: usize = 200;
+
+/// Errors from the Adoptapet and Cloudinary API layer.
+/// `endpoint` names the call (e.g. "pet_details") rather than the full URL.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    /// The request never produced a response (timeout, connection failure, ...).
+    #[error("{endpoint} request failed: {reason}")]
+    Transport {
+        endpoint: String,
+        reason: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The server answered with a non-success status.
+    #[error("{endpoint} returned HTTP {status}: {snippet}")]
+    HttpStatus {
+        endpoint: String,
+        status: StatusCode,
+        snippet: String,
+    },
+
+    /// The response body wasn't the JSON shape we expected.
+    #[error("{endpoint} returned unexpected JSON ({source}): {snippet}")]
+    Decode {
+        endpoint: String,
+        snippet: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The response decoded fine but lacked the data we asked for.
+    #[error("{endpoint} response is missing {what}")]
+    MissingData { endpoint: String, what: String },
+
+    /// The API answered with an error payload instead of data.
+    #[error("{endpoint} returned an error: {message}")]
+    Adoptapet { endpoint: String, message: String },
+
+    /// Replay mode found no recorded response for the request.
+    #[error("{endpoint} has no recorded response for {url}")]
+    NotRecorded { endpoint: String, url: String },
+}
+
+impl ApiError {
+    /// Wrap a reqwest error, masking the API key in the URL it carries.
+    pub fn transport(endpoint: &str, source: reqwest::Error) -> Self {
+        let reason = if source.is_timeout() {
+            "timed out"
+        } else if source.is_connect() {
+            "connection failed"
+        } else if source.is_body() || source.is_decode() {
+            "could not read response body"
+        } else {
+            "request error"
+        };
+
+        ApiError::Transport {
+            endpoint: endpoint.to_string(),
+            reason: reason.to_string(),
+            source: redact_error(source),
+        }
+    }
+
+    pub fn http_status(endpoint: &str, status: StatusCode, body: &str) -> Self {
+        ApiError::HttpStatus {
+            endpoint: endpoint.to_string(),
+            status,
+            snippet: snippet(body),
+        }
+    }
+
+    pub fn decode(endpoint: &str, body: &str, source: serde_json::Error) -> Self {
+        ApiError::Decode {
+            endpoint: endpoint.to_string(),
+            snippet: snippet(body),
+            source,
+        }
+    }
+
+    pub fn missing(endpoint: &str, what: impl Into<String>) -> Self {
+        ApiError::MissingData {
+            endpoint: endpoint.to_string(),
+            what: what.into(),
+        }
+    }
+
+    /// Short machine-readable name for the failure kind, used in the run summary.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::Transport { .. } => "transport",
+            ApiError::HttpStatus { .. } => "http_status",
+            ApiError::Decode { .. } => "decode",
+            ApiError::MissingData { .. } => "missing_data",
+            ApiError::Adoptapet { .. } => "api_error",
+            ApiError::NotRecorded { .. } => "not_recorded",
+        }
+    }
+}
+
+/// Collapse whitespace and truncate a response body for inclusion in an error message.
+fn snippet(body: &str) -> String {
+    let collapsed = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() > SNIPPET_LEN {
+        let truncated: String = collapsed.chars().take(SNIPPET_LEN).collect();
+        format!("{}...", truncated)
+    } else if collapsed.is_empty() {
+        "<empty body>".to_string()
+    } else {
+        collapsed
+    }
+}
+
+/// Why an Adoptapet client couldn't be built.
+#[derive(Debug, Error)]
+pub enum BuildError {
+    /// Every live request needs the key; only replays can do without.
+    #[error("an Adoptapet API key is required unless replaying fixtures")]
+    MissingApiKey,
+
+    #[error("could not create the HTTP client: {0}")]
+    Client(#[from] reqwest::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_truncates_and_collapses() {
+        assert_eq!(snippet("  {\n  \"a\": 1\n}  "), "{ \"a\": 1 }");
+        assert_eq!(snippet(""), "<empty body>");
+
+        let long = "é".repeat(SNIPPET_LEN + 10);
+        let result = snippet(&long);
+        assert_eq!(result.chars().count(), SNIPPET_LEN + 3);
+        assert!(result.ends_with("..."));
+    }
+
+    #[test]
+    fn test_decode_error_message_includes_body() {
+        let body = "<html>Bad Gateway</html>";
+        let source = serde_json::from_str::<serde_json::Value>(body).unwrap_err();
+        let error = ApiError::decode("pet_details", body, source);
+
+        let message = error.to_string();
+        assert!(message.starts_with("pet_details returned unexpected JSON"));
+        assert!(message.ends_with(body));
+        assert_eq!(error.kind(), "decode");
+    }
+}
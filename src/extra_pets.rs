@@ -0,0 +1,494 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use futures::future::join_all;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use crate::api::Result;
+use crate::cloudinary::CloudinaryUrl;
+use crate::fixtures::Fixtures;
+use crate::limits::{ConcurrencyLimits, Host};
+use crate::models::{capitalize_first, AttributeFlags, Descriptions, Pet, PhotoMetadata};
+use crate::probe::probe_dimensions;
+use crate::retry::RetryPolicy;
+use crate::source::{Listing, PetSource};
+
+/// Separator between URLs in the `photo_urls` column.
+const PHOTO_URL_SEPARATOR: char = '|';
+
+/// A row of the extra pets CSV, exactly as written. The file needs a header row;
+/// columns may appear in any order and optional columns may be left out entirely.
+///
+/// | column            | required | format                                          |
+/// |-------------------|----------|-------------------------------------------------|
+/// | `id`              | yes      | unique within the file and the source's listing |
+/// | `name`            | yes      |                                                 |
+/// | `type`            | yes      | Dog, Cat, Rabbit, ...                           |
+/// | `breed`           |          |                                                 |
+/// | `age`             |          | Baby, Young, Adult, Senior                      |
+/// | `sex`             |          | Male, Female, M or F                            |
+/// | `size`            |          |                                                 |
+/// | `url`             | yes      | http(s) URL of the pet's page                   |
+/// | `shelter_id`      |          |                                                 |
+/// | `photo_urls`      |          | http(s) URLs separated by `\|`, primary first   |
+/// | `description`     |          | HTML or plain text                              |
+/// | `color`           |          |                                                 |
+/// | `good_with_cats`, `good_with_dogs`, `good_with_kids`, `housetrained`, `shots_current`, `spayed_neutered`, `special_needs`, `declawed` | | yes/no, true/false or 1/0; blank means unknown |
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CsvRow {
+    id: Option<String>,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    pet_type: Option<String>,
+    breed: Option<String>,
+    age: Option<String>,
+    sex: Option<String>,
+    size: Option<String>,
+    url: Option<String>,
+    shelter_id: Option<String>,
+    photo_urls: Option<String>,
+    description: Option<String>,
+    color: Option<String>,
+    good_with_cats: Option<String>,
+    good_with_dogs: Option<String>,
+    good_with_kids: Option<String>,
+    housetrained: Option<String>,
+    shots_current: Option<String>,
+    spayed_neutered: Option<String>,
+    special_needs: Option<String>,
+    declawed: Option<String>,
+}
+
+/// A validated pet from the extra pets CSV.
+#[derive(Debug, Clone)]
+pub struct ExtraPet {
+    pub id: String,
+    pub name: String,
+    pub pet_type: String,
+    pub breed: Option<String>,
+    pub age: Option<String>,
+    pub sex: Option<String>,
+    pub size: Option<String>,
+    pub url: String,
+    pub shelter_id: Option<String>,
+    pub photo_urls: Vec<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub flags: AttributeFlags,
+}
+
+/// A problem with one line of the extra pets CSV.
+#[derive(Debug, thiserror::Error)]
+#[error("line {line}: {message}")]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// Read and validate the extra pets CSV, failing with every invalid row listed.
+pub fn load_extra_pets(path: &Path) -> anyhow::Result<Vec<ExtraPet>> {
+    let file = File::open(path).with_context(|| format!("Could not open {:?}", path))?;
+    parse_extra_pets(file).map_err(|errors| {
+        let lines: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+        anyhow!(
+            "{:?} has {} invalid rows:\n{}",
+            path,
+            errors.len(),
+            lines.join("\n")
+        )
+    })
+}
+
+/// Parse and validate every row, collecting all problems rather than stopping at the first.
+pub fn parse_extra_pets(reader: impl Read) -> std::result::Result<Vec<ExtraPet>, Vec<RowError>> {
+    let mut csv = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = csv
+        .headers()
+        .map_err(|e| vec![row_error(1, e.to_string())])?
+        .clone();
+
+    let mut pets = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen_ids = HashSet::new();
+
+    for record in csv.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                errors.push(row_error(line, e.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+
+        let row: CsvRow = match record.deserialize(Some(&headers)) {
+            Ok(row) => row,
+            Err(e) => {
+                errors.push(row_error(line, e.to_string()));
+                continue;
+            }
+        };
+
+        match validate_row(row) {
+            Ok(pet) if !seen_ids.insert(pet.id.clone()) => {
+                errors.push(row_error(line, format!("duplicate id {:?}", pet.id)));
+            }
+            Ok(pet) => pets.push(pet),
+            Err(problems) => errors.extend(problems.into_iter().map(|m| row_error(line, m))),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(pets)
+    } else {
+        Err(errors)
+    }
+}
+
+fn row_error(line: u64, message: String) -> RowError {
+    RowError { line, message }
+}
+
+/// Check a row's values, returning every problem found.
+fn validate_row(row: CsvRow) -> std::result::Result<ExtraPet, Vec<String>> {
+    let mut problems = Vec::new();
+
+    for (value, column) in [
+        (&row.id, "id"),
+        (&row.name, "name"),
+        (&row.pet_type, "type"),
+        (&row.url, "url"),
+    ] {
+        if value.is_none() {
+            problems.push(format!("{} is required", column));
+        }
+    }
+
+    let sex = match row.sex.as_deref().map(|s| s.to_lowercase()).as_deref() {
+        None => None,
+        Some("m") | Some("male") => Some("Male".to_string()),
+        Some("f") | Some("female") => Some("Female".to_string()),
+        Some(_) => {
+            problems.push(format!("sex must be Male or Female, got {:?}", row.sex));
+            None
+        }
+    };
+
+    if let Some(url) = &row.url {
+        if !is_web_url(url) {
+            problems.push(format!("url is not an http(s) URL: {:?}", url));
+        }
+    }
+
+    let photo_urls: Vec<String> = row
+        .photo_urls
+        .as_deref()
+        .unwrap_or_default()
+        .split(PHOTO_URL_SEPARATOR)
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect();
+    for url in &photo_urls {
+        if !is_web_url(url) {
+            problems.push(format!("photo_urls has a non-http(s) URL: {:?}", url));
+        }
+    }
+
+    let mut flag = |value: Option<String>, column: &str| match parse_flag(value.as_deref()) {
+        Ok(flag) => flag,
+        Err(()) => {
+            problems.push(format!("{} must be yes or no, got {:?}", column, value));
+            None
+        }
+    };
+    let flags = AttributeFlags {
+        good_with_cats: flag(row.good_with_cats, "good_with_cats"),
+        good_with_dogs: flag(row.good_with_dogs, "good_with_dogs"),
+        good_with_kids: flag(row.good_with_kids, "good_with_kids"),
+        housetrained: flag(row.housetrained, "housetrained"),
+        shots_current: flag(row.shots_current, "shots_current"),
+        spayed_neutered: flag(row.spayed_neutered, "spayed_neutered"),
+        special_needs: flag(row.special_needs, "special_needs"),
+        declawed: flag(row.declawed, "declawed"),
+    };
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let pet_type = match row.pet_type.as_deref().map(|t| t.to_lowercase()).as_deref() {
+        Some("dog") => "Dog".to_string(),
+        Some("cat") => "Cat".to_string(),
+        Some(other) => capitalize_first(other),
+        None => "Other".to_string(),
+    };
+
+    Ok(ExtraPet {
+        id: row.id.unwrap_or_default(),
+        name: row.name.unwrap_or_default(),
+        pet_type,
+        breed: row.breed,
+        age: row.age.as_deref().map(capitalize_first),
+        sex,
+        size: row.size,
+        url: row.url.unwrap_or_default(),
+        shelter_id: row.shelter_id,
+        photo_urls,
+        description: row.description,
+        color: row.color,
+        flags,
+    })
+}
+
+/// Parse a yes/no cell; blank means unknown.
+fn parse_flag(value: Option<&str>) -> std::result::Result<Option<bool>, ()> {
+    match value.map(str::to_lowercase).as_deref() {
+        None => Ok(None),
+        Some("yes" | "y" | "true" | "1") => Ok(Some(true)),
+        Some("no" | "n" | "false" | "0") => Ok(Some(false)),
+        Some(_) => Err(()),
+    }
+}
+
+fn is_web_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Append extra pets to the source's pets. An extra pet whose ID the source
+/// already listed is skipped with a warning; the source's data wins.
+pub fn merge_extra_pets(pets: &mut Vec<Pet>, extra_pets: Vec<Pet>) -> usize {
+    let listed: HashSet<String> = pets.iter().map(|pet| pet.id.clone()).collect();
+    let before = pets.len();
+    for pet in extra_pets {
+        if listed.contains(&pet.id) {
+            eprintln!(
+                "Warning: extra pet {} ({}) is already listed by the source; skipping",
+                pet.name, pet.id
+            );
+        } else {
+            pets.push(pet);
+        }
+    }
+    pets.len() - before
+}
+
+impl ExtraPet {
+    /// Convert an extra pet + probed photo metadata to our simplified model.
+    pub fn into_pet(self, photos: Vec<PhotoMetadata>) -> Pet {
+        let descriptions = Descriptions::from_html(self.description.as_deref());
+
+        Pet {
+            id: self.id,
+            name: self.name,
+            pet_type: self.pet_type,
+            breed: self.breed,
+            age: self.age,
+            sex: self.sex,
+            size: self.size,
+            url: self.url,
+            shelter_id: self.shelter_id,
+            photo_url: self.photo_urls.into_iter().next(),
+            photo_blurhash: None,
+            photo_lqip: None,
+            photo_dominant_color: None,
+            thumbnail_url: None,
+            hero_url: None,
+            social_url: None,
+            photos,
+            description: descriptions.text,
+            description_html: descriptions.html,
+            description_markdown: descriptions.markdown,
+            short_description: descriptions.short,
+            color: self.color,
+            attributes: self.flags.to_attributes(),
+            littermates: Vec::new(),
+        }
+    }
+}
+
+/// The extra pets CSV as a [`PetSource`]. Rows are validated up front by
+/// [`load_extra_pets`]; photo dimensions are probed from the image headers.
+/// Photos on Adoptapet's Cloudinary get `srcset` renditions like listed pets'.
+pub struct ExtraPetsSource {
+    pets: Vec<ExtraPet>,
+    client: Client,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    media_base_url: String,
+    srcset_widths: Vec<u32>,
+    fixtures: Option<Fixtures>,
+}
+
+impl ExtraPetsSource {
+    pub fn new(
+        pets: Vec<ExtraPet>,
+        client: Client,
+        retry: RetryPolicy,
+        limits: ConcurrencyLimits,
+        media_base_url: String,
+        srcset_widths: Vec<u32>,
+        fixtures: Option<Fixtures>,
+    ) -> Self {
+        Self {
+            pets,
+            client,
+            retry,
+            limits,
+            media_base_url,
+            srcset_widths,
+            fixtures,
+        }
+    }
+}
+
+impl Listing for ExtraPet {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PetSource for ExtraPetsSource {
+    type Listing = ExtraPet;
+    type Details = ();
+
+    fn name(&self) -> &str {
+        "extra pets file"
+    }
+
+    async fn list_pets(&self) -> Result<Vec<ExtraPet>> {
+        Ok(self.pets.clone())
+    }
+
+    async fn fetch_details(&self, _listing: &ExtraPet) -> Result<()> {
+        Ok(())
+    }
+
+    async fn resolve_photos(
+        &self,
+        listing: &ExtraPet,
+        _details: Option<&()>,
+    ) -> Vec<Result<PhotoMetadata>> {
+        let futures = listing.photo_urls.iter().map(|url| async move {
+            let _permit = self.limits.acquire(Host::Media).await;
+            let (width, height) =
+                probe_dimensions(&self.client, &self.retry, url, self.fixtures.as_ref()).await?;
+            let photo = PhotoMetadata::new(url.clone(), width, height);
+            Ok(match CloudinaryUrl::parse_on(url, &self.media_base_url) {
+                Ok(image) => photo.with_cloudinary_renditions(
+                    &image,
+                    &self.srcset_widths,
+                    &self.media_base_url,
+                ),
+                Err(_) => photo,
+            })
+        });
+
+        join_all(futures).await
+    }
+
+    fn convert(&self, listing: ExtraPet, _details: Option<()>, photos: Vec<PhotoMetadata>) -> Pet {
+        listing.into_pet(photos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extra_pets() {
+        let csv = "\
+id,name,type,url,sex,age,photo_urls,good_with_cats,housetrained,description
+courtesy-1,Biscuit,dog,https://example.com/biscuit,f,young,https://example.com/a.jpg | https://example.com/b.jpg,yes,,<p>Sweet girl</p>
+intake-2, Mittens ,Cat,https://example.com/mittens,Male,,,no,1,
+";
+        let pets = parse_extra_pets(csv.as_bytes()).unwrap();
+        assert_eq!(pets.len(), 2);
+
+        let biscuit = pets[0].clone().into_pet(vec![]);
+        assert_eq!(biscuit.id, "courtesy-1");
+        assert_eq!(biscuit.pet_type, "Dog");
+        assert_eq!(biscuit.url, "https://example.com/biscuit");
+        assert_eq!(biscuit.sex.as_deref(), Some("Female"));
+        assert_eq!(biscuit.age.as_deref(), Some("Young"));
+        assert_eq!(pets[0].photo_urls.len(), 2);
+        assert_eq!(
+            biscuit.photo_url.as_deref(),
+            Some("https://example.com/a.jpg")
+        );
+        assert_eq!(biscuit.description.as_deref(), Some("Sweet girl"));
+        let keys: Vec<_> = biscuit.attributes.iter().map(|a| a.key.as_str()).collect();
+        assert_eq!(keys, ["good_with_cats"]);
+
+        assert_eq!(pets[1].name, "Mittens");
+        assert!(pets[1].photo_urls.is_empty());
+    }
+
+    #[test]
+    fn test_parse_extra_pets_reports_every_bad_line() {
+        let csv = "\
+id,name,type,sex,url,photo_urls,declawed
+1,Rex,Dog,,https://example.com/rex,,
+2,,Dog,unknown,,,
+3,Tom,Cat,,ftp://example.com,not a url,maybe
+1,Rex again,Dog,,https://example.com/rex,,
+";
+        let errors = parse_extra_pets(csv.as_bytes()).unwrap_err();
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 3: name is required",
+                "line 3: url is required",
+                "line 3: sex must be Male or Female, got Some(\"unknown\")",
+                "line 4: url is not an http(s) URL: \"ftp://example.com\"",
+                "line 4: photo_urls has a non-http(s) URL: \"not a url\"",
+                "line 4: declawed must be yes or no, got Some(\"maybe\")",
+                "line 5: duplicate id \"1\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_extra_pets_keeps_listed_pet() {
+        let pet = |id: &str, name: &str| {
+            ExtraPet {
+                id: id.to_string(),
+                name: name.to_string(),
+                pet_type: "Dog".to_string(),
+                breed: None,
+                age: None,
+                sex: None,
+                size: None,
+                url: format!("https://example.com/{}", id),
+                shelter_id: None,
+                photo_urls: vec![],
+                description: None,
+                color: None,
+                flags: AttributeFlags::default(),
+            }
+            .into_pet(vec![])
+        };
+        let mut pets = vec![pet("1", "Listed")];
+
+        let added = merge_extra_pets(&mut pets, vec![pet("1", "Extra"), pet("2", "Courtesy")]);
+
+        assert_eq!(added, 1);
+        let names: Vec<_> = pets.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Listed", "Courtesy"]);
+    }
+}
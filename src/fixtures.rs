@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, SET_COOKIE};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::redact::strip_api_key;
+
+/// Whether HTTP exchanges are being captured or played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixtureMode {
+    /// Make real requests and save every response.
+    Record,
+    /// Serve every request from saved responses, never touching the network.
+    Replay,
+}
+
+/// A recorded HTTP exchange. The URL has the API key removed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub url: String,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The body as text, or base64 for binary bodies (images)
+    pub body: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub base64: bool,
+}
+
+/// Directory of recorded exchanges, one JSON file per request:
+/// `<dir>/<endpoint>/<query, or file name and path hash>.json`.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+    mode: FixtureMode,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>, mode: FixtureMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    /// Save a response received for `url`.
+    pub fn record(
+        &self,
+        endpoint: &str,
+        url: &Url,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+    ) {
+        self.save(endpoint, url, status, headers, body.to_string(), false);
+    }
+
+    /// Download a binary response through the fixtures: loaded from disk when
+    /// replaying, otherwise fetched with `download` (and saved when recording).
+    /// Whatever the status, it is returned for the caller to judge.
+    pub async fn bytes<F, Fut>(
+        &self,
+        endpoint: &str,
+        url: &str,
+        download: F,
+    ) -> Result<(StatusCode, Vec<u8>), ApiError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(StatusCode, Vec<u8>), ApiError>>,
+    {
+        let parsed = Url::parse(url)
+            .map_err(|_| ApiError::missing(endpoint, format!("a valid URL ({:?})", url)))?;
+        match self.mode {
+            FixtureMode::Replay => {
+                let (status, _, body) = self.replay(endpoint, &parsed)?;
+                let bytes = STANDARD
+                    .decode(&body)
+                    .map_err(|e| ApiError::missing(endpoint, format!("recorded bytes ({})", e)))?;
+                Ok((status, bytes))
+            }
+            FixtureMode::Record => {
+                let (status, bytes) = download().await?;
+                let body = STANDARD.encode(&bytes);
+                self.save(endpoint, &parsed, status, &HeaderMap::new(), body, true);
+                Ok((status, bytes))
+            }
+        }
+    }
+
+    fn save(
+        &self,
+        endpoint: &str,
+        url: &Url,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: String,
+        base64: bool,
+    ) {
+        let exchange = Exchange {
+            url: strip_api_key(url),
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| *name != SET_COOKIE)
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body,
+            base64,
+        };
+
+        let path = self.exchange_path(endpoint, url);
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                fs::write(
+                    &path,
+                    serde_json::to_string_pretty(&exchange).unwrap_or_default(),
+                )
+            });
+        if let Err(e) = result {
+            eprintln!("Warning: could not record {:?}: {}", path, e);
+        }
+    }
+
+    /// Load the response recorded for `url`.
+    pub fn replay(
+        &self,
+        endpoint: &str,
+        url: &Url,
+    ) -> Result<(StatusCode, HeaderMap, String), ApiError> {
+        let path = self.exchange_path(endpoint, url);
+        let not_recorded = || ApiError::NotRecorded {
+            endpoint: endpoint.to_string(),
+            url: strip_api_key(url),
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|_| not_recorded())?;
+        let exchange: Exchange = serde_json::from_str(&contents)
+            .map_err(|e| ApiError::decode(endpoint, &contents, e))?;
+
+        let status = StatusCode::from_u16(exchange.status).map_err(|_| not_recorded())?;
+        let headers = exchange
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect();
+
+        Ok((status, headers, exchange.body))
+    }
+
+    fn exchange_path(&self, endpoint: &str, url: &Url) -> PathBuf {
+        self.dir
+            .join(endpoint)
+            .join(format!("{}.json", fixture_name(url)))
+    }
+}
+
+/// Human-readable file name for a request: its query parameters (minus the API key
+/// and output format), or when there is no query the last path segment plus a
+/// hash of the whole path, so images of the same name in different folders
+/// don't overwrite each other.
+/// pets_at_shelter?key=..&shelter_id=1&start_number=1 -> shelter_id-1_start_number-1
+/// image/upload/fl_getinfo/1268757503 -> 1268757503-<8 hex digits>
+fn fixture_name(url: &Url) -> String {
+    let params: Vec<String> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "key" && name != "output")
+        .map(|(name, value)| format!("{}-{}", name, value))
+        .collect();
+
+    let name = if params.is_empty() {
+        let last = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .unwrap_or("index");
+        let hash = format!("{:x}", Sha256::digest(url.path()));
+        format!("{}-{}", last, &hash[..8])
+    } else {
+        params.join("_")
+    };
+
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_name() {
+        let url = Url::parse(
+            "https://api.adoptapet.com/search/pets_at_shelter?key=secret&shelter_id=83349&start_number=1&end_number=100&output=json",
+        )
+        .unwrap();
+        assert_eq!(
+            fixture_name(&url),
+            "shelter_id-83349_start_number-1_end_number-100"
+        );
+
+        let url =
+            Url::parse("https://media.adoptapet.com/image/upload/fl_getinfo/1268757503").unwrap();
+        assert!(fixture_name(&url).starts_with("1268757503-"));
+
+        let a = Url::parse("https://example.org/a/dog.jpg").unwrap();
+        let b = Url::parse("https://example.org/b/dog.jpg").unwrap();
+        assert!(fixture_name(&a).starts_with("dog.jpg-"));
+        assert_ne!(fixture_name(&a), fixture_name(&b));
+    }
+}
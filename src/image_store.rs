@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// What we know about a Cloudinary image. An image ID's content never
+/// changes, so a record never goes stale.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageRecord {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phash: Option<String>,
+}
+
+impl ImageRecord {
+    /// Whether anything derived from the preview rendition is missing,
+    /// e.g. because the record predates it.
+    pub fn needs_preview(&self) -> bool {
+        self.blurhash.is_none() || self.palette.is_empty() || self.phash.is_none()
+    }
+}
+
+/// The store as written to disk.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    images: BTreeMap<String, ImageRecord>,
+}
+
+/// Persistent image metadata keyed by Cloudinary image ID (version and public
+/// ID, so a re-upload gets a fresh record), kept next to the output so a run
+/// only asks Cloudinary about photos it hasn't seen before.
+/// Failures to read or write the store are logged and otherwise ignored.
+#[derive(Debug)]
+pub struct ImageStore {
+    path: PathBuf,
+    images: Mutex<BTreeMap<String, ImageRecord>>,
+    added: Mutex<usize>,
+    /// Image IDs looked up or added during this run
+    used: Mutex<HashSet<String>>,
+}
+
+impl ImageStore {
+    /// Load the store at `path`, starting empty if it is missing or unreadable.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let images = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<StoreFile>(&contents) {
+                Ok(file) => file.images,
+                Err(e) => {
+                    eprintln!("Warning: ignoring unreadable image store {:?}: {}", path, e);
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+
+        Self {
+            path,
+            images: Mutex::new(images),
+            added: Mutex::new(0),
+            used: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn get(&self, image_id: &str) -> Option<ImageRecord> {
+        self.used.lock().unwrap().insert(image_id.to_string());
+        self.images.lock().unwrap().get(image_id).cloned()
+    }
+
+    pub fn insert(&self, image_id: &str, record: ImageRecord) {
+        self.used.lock().unwrap().insert(image_id.to_string());
+        let previous = self
+            .images
+            .lock()
+            .unwrap()
+            .insert(image_id.to_string(), record);
+        if previous.is_none() {
+            *self.added.lock().unwrap() += 1;
+        }
+    }
+
+    /// Drop records of images this run didn't use, so photos of adopted pets
+    /// don't pile up. Returns how many were dropped.
+    pub fn prune(&self) -> usize {
+        let used = self.used.lock().unwrap();
+        let mut images = self.images.lock().unwrap();
+        let before = images.len();
+        images.retain(|image_id, _| used.contains(image_id));
+        before - images.len()
+    }
+
+    /// Write the store back to disk.
+    pub fn save(&self) {
+        let added = *self.added.lock().unwrap();
+        let file = StoreFile {
+            images: self.images.lock().unwrap().clone(),
+        };
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                fs::write(
+                    &self.path,
+                    serde_json::to_string_pretty(&file).unwrap_or_default(),
+                )
+            });
+        match result {
+            Ok(()) => println!(
+                "Saved {} image records ({} new) to {:?}",
+                file.images.len(),
+                added,
+                self.path
+            ),
+            Err(e) => eprintln!("Warning: could not save image store {:?}: {}", self.path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_store_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("update-pets-image-store-{}", std::process::id()));
+        let path = dir.join("pets.images.json");
+
+        let store = ImageStore::load(&path);
+        assert_eq!(store.get("1268757503"), None);
+        let record = ImageRecord {
+            width: 750,
+            height: 1000,
+            ..Default::default()
+        };
+        store.insert("1268757503", record.clone());
+        store.save();
+
+        let reloaded = ImageStore::load(&path);
+        assert_eq!(reloaded.get("1268757503"), Some(record));
+
+        // Only images used since loading survive pruning
+        let store = ImageStore::load(&path);
+        store.insert("1268757504", ImageRecord::default());
+        assert_eq!(store.prune(), 1);
+        assert_eq!(store.get("1268757503"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
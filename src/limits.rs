@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Default number of requests in flight across all hosts.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 16;
+/// Default number of requests in flight to the Adoptapet search API.
+pub const DEFAULT_MAX_API_CONCURRENCY: usize = 8;
+/// Default number of requests in flight to the Cloudinary media host.
+pub const DEFAULT_MAX_MEDIA_CONCURRENCY: usize = 8;
+
+/// The hosts we talk to, each with its own concurrency budget.
+#[derive(Debug, Clone, Copy)]
+pub enum Host {
+    /// The Adoptapet search API (pets_at_shelter, pet_details).
+    Api,
+    /// The Cloudinary media host (fl_getinfo, image header probes).
+    Media,
+}
+
+/// Caps the number of in-flight requests, both overall and per host.
+/// Clones share the same budgets, so every component of a run draws from one pool.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimits {
+    global: Arc<Semaphore>,
+    api: Arc<Semaphore>,
+    media: Arc<Semaphore>,
+}
+
+/// Permits held for the duration of a single request.
+pub struct RequestPermit<'a> {
+    _global: SemaphorePermit<'a>,
+    _host: SemaphorePermit<'a>,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_MAX_CONCURRENCY,
+            DEFAULT_MAX_API_CONCURRENCY,
+            DEFAULT_MAX_MEDIA_CONCURRENCY,
+        )
+    }
+}
+
+impl ConcurrencyLimits {
+    /// Create limits; a limit of zero is treated as one so requests can still make progress.
+    pub fn new(global: usize, api: usize, media: usize) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(global.max(1))),
+            api: Arc::new(Semaphore::new(api.max(1))),
+            media: Arc::new(Semaphore::new(media.max(1))),
+        }
+    }
+
+    /// Wait for a free slot on `host` and in the global budget.
+    /// The host permit is taken first so requests queued for a busy host
+    /// don't hold global slots that the other host could use.
+    pub async fn acquire(&self, host: Host) -> RequestPermit<'_> {
+        let semaphore = match host {
+            Host::Api => &self.api,
+            Host::Media => &self.media,
+        };
+        let host_permit = semaphore.acquire().await.expect("semaphore closed");
+        let global_permit = self.global.acquire().await.expect("semaphore closed");
+
+        RequestPermit {
+            _global: global_permit,
+            _host: host_permit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_host_limits_are_independent() {
+        let limits = ConcurrencyLimits::new(4, 1, 1);
+
+        let api = limits.acquire(Host::Api).now_or_never();
+        assert!(api.is_some());
+        assert!(limits.acquire(Host::Api).now_or_never().is_none());
+        assert!(limits.acquire(Host::Media).now_or_never().is_some());
+
+        drop(api);
+        assert!(limits.acquire(Host::Api).now_or_never().is_some());
+    }
+
+    #[test]
+    fn test_global_limit_caps_all_hosts() {
+        let limits = ConcurrencyLimits::new(1, 4, 4);
+
+        let _api = limits.acquire(Host::Api).now_or_never().unwrap();
+        assert!(limits.acquire(Host::Media).now_or_never().is_none());
+    }
+
+    #[test]
+    fn test_clones_share_budgets() {
+        let limits = ConcurrencyLimits::new(1, 4, 4);
+        let shared = limits.clone();
+
+        let _api = limits.acquire(Host::Api).now_or_never().unwrap();
+        assert!(shared.acquire(Host::Media).now_or_never().is_none());
+    }
+}
@@ -1,87 +1,629 @@
 mod api;
+mod cache;
+mod cloudinary;
+mod color;
+mod duplicates;
+mod error;
+mod extra_pets;
+mod fixtures;
+mod image_store;
+mod limits;
+mod mirror;
 mod models;
+mod overrides;
+mod petfinder;
+mod phash;
+mod presets;
+mod preview;
+mod primary;
+mod probe;
+mod reachability;
+mod redact;
+mod retry;
+mod source;
 
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use chrono::Utc;
-use clap::Parser;
-use futures::future::join_all;
+use clap::{Parser, ValueEnum};
 
-use api::AdoptapetApi;
-use models::{Pet, PetsData};
+use api::{
+    build_http_client, AdoptapetApi, AdoptapetSource, DEFAULT_BASE_URL,
+    DEFAULT_CONNECT_TIMEOUT_SECS, DEFAULT_PAGE_SIZE, DEFAULT_READ_TIMEOUT_SECS,
+    DEFAULT_TIMEOUT_SECS, DEFAULT_USER_AGENT,
+};
+use cache::{
+    CacheTtls, ResponseCache, DEFAULT_DETAILS_TTL_HOURS, DEFAULT_IMAGE_INFO_TTL_HOURS,
+    DEFAULT_LISTING_TTL_HOURS,
+};
+use duplicates::{collapse_duplicate_photos, find_shared_photos, link_littermates};
+use extra_pets::{load_extra_pets, merge_extra_pets, ExtraPetsSource};
+use fixtures::{FixtureMode, Fixtures};
+use image_store::ImageStore;
+use limits::{
+    ConcurrencyLimits, DEFAULT_MAX_API_CONCURRENCY, DEFAULT_MAX_CONCURRENCY,
+    DEFAULT_MAX_MEDIA_CONCURRENCY,
+};
+use mirror::{MirrorConfig, PhotoMirror, DEFAULT_MIRROR_WIDTHS};
+use models::{FailureSummary, PetsData, DEFAULT_SRCSET_WIDTHS, MEDIA_BASE_URL};
+use overrides::Overrides;
+use petfinder::{PetfinderApi, PetfinderSource, DEFAULT_PETFINDER_BASE_URL};
+use presets::Presets;
+use primary::{
+    select_primary_photos, PhotoPolicy, DEFAULT_MIN_PHOTO_HEIGHT, DEFAULT_MIN_PHOTO_WIDTH,
+};
+use reachability::{PhotoChecker, DEFAULT_MAX_FAILED_PHOTO_SHARE, DEFAULT_PHOTO_CHECK_TTL_HOURS};
+use redact::ApiKey;
+use retry::{RetryPolicy, DEFAULT_BASE_DELAY_MS, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_DELAY_MS};
+use source::{collect_pets, PetSource};
+
+/// Where pets are fetched from.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SourceKind {
+    Adoptapet,
+    /// Petfinder v2 API; --record, --replay and --cache-dir apply to Adoptapet only
+    Petfinder,
+}
 
 /// Fetch pets from Adoptapet API and write to JSON file.
 #[derive(Parser, Debug)]
 #[command(name = "update-pets")]
-#[command(about = "Fetch pets from Adoptapet API and write to JSON file")]
+#[command(about = "Fetch pets from Adoptapet (or Petfinder) and write to JSON file")]
 struct Args {
-    /// Adoptapet API key
+    /// Where to fetch pets from
+    #[arg(long, value_enum, default_value_t = SourceKind::Adoptapet)]
+    source: SourceKind,
+
+    /// Adoptapet API key (not needed with --replay)
     #[arg(long, env = "ADOPTAPET_API_KEY")]
-    api_key: String,
+    api_key: Option<ApiKey>,
+
+    /// Shelter IDs, comma separated or repeated; pets listed under several are kept once
+    #[arg(
+        long,
+        env = "SHELTER_ID",
+        default_value = "83349",
+        value_delimiter = ',',
+        num_args = 1..
+    )]
+    shelter_id: Vec<String>,
+
+    /// Petfinder OAuth client ID
+    #[arg(long, env = "PETFINDER_CLIENT_ID")]
+    petfinder_client_id: Option<String>,
+
+    /// Petfinder OAuth client secret
+    #[arg(long, env = "PETFINDER_CLIENT_SECRET")]
+    petfinder_client_secret: Option<ApiKey>,
+
+    /// Petfinder organization IDs, comma separated or repeated
+    #[arg(long, env = "PETFINDER_ORGANIZATION", value_delimiter = ',')]
+    petfinder_organization: Vec<String>,
+
+    /// Petfinder v2 API base URL
+    #[arg(long, default_value = DEFAULT_PETFINDER_BASE_URL)]
+    petfinder_base_url: String,
+
+    /// Adoptapet search API base URL
+    #[arg(long, env = "ADOPTAPET_BASE_URL", default_value = DEFAULT_BASE_URL)]
+    base_url: String,
+
+    /// Cloudinary media host base URL used for fl_getinfo
+    #[arg(long, env = "ADOPTAPET_MEDIA_BASE_URL", default_value = MEDIA_BASE_URL)]
+    media_base_url: String,
+
+    /// Seconds allowed to establish a connection
+    #[arg(long, default_value_t = DEFAULT_CONNECT_TIMEOUT_SECS)]
+    connect_timeout_secs: u64,
+
+    /// Seconds allowed between reads of a response
+    #[arg(long, default_value_t = DEFAULT_READ_TIMEOUT_SECS)]
+    read_timeout_secs: u64,
+
+    /// Seconds allowed for a whole request
+    #[arg(long, default_value_t = DEFAULT_TIMEOUT_SECS)]
+    timeout_secs: u64,
+
+    /// User-Agent sent with every request
+    #[arg(long, default_value = DEFAULT_USER_AGENT)]
+    user_agent: String,
+
+    /// Number of pets to request per pets_at_shelter page
+    #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+
+    /// Maximum attempts per HTTP request, including the first
+    #[arg(long, default_value_t = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u32,
+
+    /// Delay before the first retry in milliseconds (doubles on each retry)
+    #[arg(long, default_value_t = DEFAULT_BASE_DELAY_MS)]
+    retry_base_delay_ms: u64,
+
+    /// Upper bound on any single retry delay in milliseconds
+    #[arg(long, default_value_t = DEFAULT_MAX_DELAY_MS)]
+    retry_max_delay_ms: u64,
+
+    /// Maximum HTTP requests in flight across all hosts
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENCY)]
+    max_concurrency: usize,
+
+    /// Maximum HTTP requests in flight to the Adoptapet API
+    #[arg(long, default_value_t = DEFAULT_MAX_API_CONCURRENCY)]
+    max_api_concurrency: usize,
+
+    /// Maximum HTTP requests in flight to the Cloudinary media host
+    #[arg(long, default_value_t = DEFAULT_MAX_MEDIA_CONCURRENCY)]
+    max_media_concurrency: usize,
+
+    /// Record every HTTP exchange (API key scrubbed) into this directory
+    #[arg(long, conflicts_with_all = ["replay", "cache_dir"])]
+    record: Option<PathBuf>,
+
+    /// Replay HTTP exchanges recorded with --record instead of using the network
+    #[arg(long, conflicts_with_all = ["cache_dir", "mirror_dir"])]
+    replay: Option<PathBuf>,
+
+    /// Directory for the on-disk HTTP response cache (disabled when unset)
+    #[arg(long, env = "CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Hours a cached pets_at_shelter page is used without revalidating
+    #[arg(long, default_value_t = DEFAULT_LISTING_TTL_HOURS)]
+    listing_cache_ttl_hours: u64,
+
+    /// Hours a cached pet_details response is used without revalidating
+    #[arg(long, default_value_t = DEFAULT_DETAILS_TTL_HOURS)]
+    details_cache_ttl_hours: u64,
+
+    /// Hours a cached fl_getinfo response is used without revalidating
+    #[arg(long, default_value_t = DEFAULT_IMAGE_INFO_TTL_HOURS)]
+    image_info_cache_ttl_hours: u64,
+
+    /// CSV of pets to publish alongside the source's listing
+    /// (columns are documented in src/extra_pets.rs)
+    #[arg(long, env = "EXTRA_PETS")]
+    extra_pets: Option<PathBuf>,
+
+    /// TOML file of staff overrides (field fixes, hidden and pinned pets), keyed by pet ID
+    #[arg(long, env = "OVERRIDES_FILE")]
+    overrides: Option<PathBuf>,
+
+    /// TOML file of Cloudinary transformations for the card, thumbnail, hero and
+    /// social photo URLs (format documented in src/presets.rs)
+    #[arg(long, env = "PRESETS_FILE")]
+    presets: Option<PathBuf>,
+
+    /// Download every photo into this directory, with resized variants,
+    /// and point the output at the copies instead of Adoptapet's CDN
+    #[arg(long, env = "MIRROR_DIR")]
+    mirror_dir: Option<PathBuf>,
 
-    /// Shelter ID
-    #[arg(long, env = "SHELTER_ID", default_value = "83349")]
-    shelter_id: String,
+    /// URL the site serves --mirror-dir from
+    #[arg(long, env = "MIRROR_BASE_URL", default_value = "/photos")]
+    mirror_base_url: String,
+
+    /// Widths, in pixels, of the resized variants of each mirrored photo
+    #[arg(long, value_delimiter = ',', default_value = DEFAULT_MIRROR_WIDTHS)]
+    mirror_widths: Vec<u32>,
+
+    /// Widths, in pixels, of the Cloudinary renditions in each photo's srcset
+    /// (capped at the photo's own width). Only photos on Adoptapet's Cloudinary
+    /// get renditions; Petfinder photos go without
+    #[arg(long, env = "SRCSET_WIDTHS", value_delimiter = ',', default_value = DEFAULT_SRCSET_WIDTHS)]
+    srcset_widths: Vec<u32>,
+
+    /// Photos narrower than this, in pixels, are dropped from the gallery
+    #[arg(long, env = "MIN_PHOTO_WIDTH", default_value_t = DEFAULT_MIN_PHOTO_WIDTH)]
+    min_photo_width: u32,
+
+    /// Photos shorter than this, in pixels, are dropped from the gallery
+    #[arg(long, env = "MIN_PHOTO_HEIGHT", default_value_t = DEFAULT_MIN_PHOTO_HEIGHT)]
+    min_photo_height: u32,
+
+    /// Don't HEAD-check the published photo URLs
+    #[arg(long, env = "SKIP_PHOTO_CHECK")]
+    skip_photo_check: bool,
+
+    /// Hours a photo URL that loaded is trusted without checking again
+    /// (kept in --cache-dir between runs)
+    #[arg(long, default_value_t = DEFAULT_PHOTO_CHECK_TTL_HOURS)]
+    photo_check_ttl_hours: u64,
+
+    /// Share of photo URLs (0 to 1) allowed to fail their check before the
+    /// run stops without writing output
+    #[arg(long, default_value_t = DEFAULT_MAX_FAILED_PHOTO_SHARE)]
+    max_failed_photo_share: f64,
+
+    /// List the other pets sharing a photo with each pet (likely littermates) in the output
+    #[arg(long, env = "LINK_LITTERMATES")]
+    link_littermates: bool,
+
+    /// Write the output even if every shelter listing came back empty and
+    /// there are no extra pets
+    #[arg(long)]
+    allow_empty: bool,
 
     /// Output JSON file path
     #[arg(short, long, default_value = "data/pets.json")]
     output: PathBuf,
 }
 
+/// Where and how the collected pets are published.
+struct PublishOptions {
+    output: PathBuf,
+    allow_empty: bool,
+    /// Record pets that share photos as littermates in the output
+    link_littermates: bool,
+    /// Minimum photo size and how the primary photo is picked
+    photo_policy: PhotoPolicy,
+    /// Pets from --extra-pets, merged after the source's pets
+    extra_pets: Option<ExtraPetsSource>,
+    /// Staff overrides from --overrides, applied to every pet
+    overrides: Option<Overrides>,
+    /// Host the run builds its Cloudinary URLs on (--media-base-url)
+    media_base_url: String,
+    /// Cloudinary transformations for each photo URL on a pet
+    presets: Presets,
+    /// Local photo mirror from --mirror-dir
+    mirror: Option<PhotoMirror>,
+    /// Checks that every published photo loads, unless --skip-photo-check
+    photo_checker: Option<PhotoChecker>,
+}
+
+/// Exit code when a shelter listing failed or came back empty, so nothing was published.
+const EXIT_LISTING_FAILED: u8 = 2;
+
+/// Marks errors that mean the shelter listing can't be trusted.
+#[derive(Debug)]
+struct ListingFailed;
+
+impl std::fmt::Display for ListingFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Shelter listing failed; not writing output")
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let args = Args::parse();
+    let secrets: Vec<ApiKey> = [args.api_key.clone(), args.petfinder_client_secret.clone()]
+        .into_iter()
+        .flatten()
+        .collect();
 
-    println!(
-        "Fetching pets from Adoptapet for shelter {}...",
-        args.shelter_id
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Errors are redacted at the source; this is the last line of defense
+            let message = secrets.iter().fold(format!("{:?}", e), |message, secret| {
+                secret.redact(&message)
+            });
+            eprintln!("Error: {}", message);
+            if e.downcast_ref::<ListingFailed>().is_some() {
+                ExitCode::from(EXIT_LISTING_FAILED)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let retry = RetryPolicy {
+        max_attempts: args.max_attempts,
+        base_delay: Duration::from_millis(args.retry_base_delay_ms),
+        max_delay: Duration::from_millis(args.retry_max_delay_ms),
+    };
+    // One budget for the whole run, shared by every component that makes requests
+    let limits = ConcurrencyLimits::new(
+        args.max_concurrency,
+        args.max_api_concurrency,
+        args.max_media_concurrency,
     );
+    let connect_timeout = Duration::from_secs(args.connect_timeout_secs);
+    let read_timeout = Duration::from_secs(args.read_timeout_secs);
+    let timeout = Duration::from_secs(args.timeout_secs);
+    let new_client = || build_http_client(connect_timeout, read_timeout, timeout, &args.user_agent);
+    // Shared by the Adoptapet API and the extra pets' image probes
+    let fixtures = match (args.source, &args.record, &args.replay) {
+        (SourceKind::Adoptapet, Some(dir), _) => {
+            Some(Fixtures::new(dir.clone(), FixtureMode::Record))
+        }
+        (SourceKind::Adoptapet, _, Some(dir)) => {
+            Some(Fixtures::new(dir.clone(), FixtureMode::Replay))
+        }
+        _ => None,
+    };
 
-    let api = AdoptapetApi::new(args.api_key);
+    // Validate the extra pets file before any network work
+    let extra_pets = match &args.extra_pets {
+        Some(path) => {
+            let pets = load_extra_pets(path)?;
+            println!("Loaded {} extra pets from {:?}", pets.len(), path);
+            Some(ExtraPetsSource::new(
+                pets,
+                new_client()?,
+                retry.clone(),
+                limits.clone(),
+                args.media_base_url.clone(),
+                args.srcset_widths.clone(),
+                fixtures.clone(),
+            ))
+        }
+        None => None,
+    };
+    let overrides = args.overrides.as_deref().map(Overrides::load).transpose()?;
+    let presets = Presets {
+        media_base_url: args.media_base_url.clone(),
+        ..args
+            .presets
+            .as_deref()
+            .map(Presets::load)
+            .transpose()?
+            .unwrap_or_default()
+    };
+    let mirror = match &args.mirror_dir {
+        Some(dir) => {
+            let config = MirrorConfig {
+                dir: dir.clone(),
+                base_url: args.mirror_base_url.clone(),
+                widths: args.mirror_widths.clone(),
+                media_base_url: args.media_base_url.clone(),
+            };
+            Some(PhotoMirror::new(
+                config,
+                new_client()?,
+                retry.clone(),
+                limits.clone(),
+            ))
+        }
+        None => None,
+    };
+    // Photo checks aren't recorded, so replays skip them
+    let photo_checker = if args.skip_photo_check || args.replay.is_some() {
+        None
+    } else {
+        Some(PhotoChecker::new(
+            new_client()?,
+            retry.clone(),
+            limits.clone(),
+            args.cache_dir
+                .as_ref()
+                .map(|dir| dir.join("photo-checks.json")),
+            Duration::from_secs(args.photo_check_ttl_hours * 3600),
+            args.max_failed_photo_share,
+        ))
+    };
+    let options = PublishOptions {
+        output: args.output.clone(),
+        allow_empty: args.allow_empty,
+        link_littermates: args.link_littermates,
+        photo_policy: PhotoPolicy {
+            min_width: args.min_photo_width,
+            min_height: args.min_photo_height,
+        },
+        extra_pets,
+        overrides,
+        media_base_url: args.media_base_url.clone(),
+        presets,
+        mirror,
+        photo_checker,
+    };
 
-    // Fetch all pets at the shelter
-    let adoptapet_pets = api.get_pets_at_shelter(&args.shelter_id).await?;
-    println!("Fetched {} pets from listing", adoptapet_pets.size());
+    match args.source {
+        SourceKind::Adoptapet => {
+            println!(
+                "Fetching pets from Adoptapet for shelter {}...",
+                args.shelter_id.join(", ")
+            );
 
-    // Fetch details for each pet in parallel to get high-res images
-    println!("Fetching pet details for high-res images...");
-    let detail_futures: Vec<_> = adoptapet_pets
-        .into_iter()
-        .map(|pet| {
-            let pet_id = pet.pet_id.clone();
-            let api = &api;
-            async move {
-                let details = api.get_pet_details(&pet_id).await;
-                (pet, details)
+            let api_key = match (args.api_key, &args.replay) {
+                (Some(api_key), _) => api_key,
+                (None, Some(_)) => ApiKey::default(),
+                (None, None) => bail!("--api-key (or ADOPTAPET_API_KEY) is required"),
+            };
+            let cache = args.cache_dir.map(|dir| {
+                let ttls = CacheTtls {
+                    listing: Duration::from_secs(args.listing_cache_ttl_hours * 3600),
+                    details: Duration::from_secs(args.details_cache_ttl_hours * 3600),
+                    image_info: Duration::from_secs(args.image_info_cache_ttl_hours * 3600),
+                };
+                ResponseCache::new(dir, ttls)
+            });
+            // Dimensions of every Cloudinary image seen so far, kept next to the
+            // output; recordings and replays don't touch it
+            let image_store = fixtures
+                .is_none()
+                .then(|| Arc::new(ImageStore::load(sibling_path(&options.output, "images"))));
+            let api = AdoptapetApi::builder()
+                .api_key(api_key)
+                .base_url(args.base_url)
+                .media_base_url(args.media_base_url)
+                .connect_timeout(connect_timeout)
+                .read_timeout(read_timeout)
+                .timeout(timeout)
+                .user_agent(args.user_agent)
+                .page_size(args.page_size)
+                .retry_policy(retry)
+                .concurrency_limits(limits)
+                .cache(cache)
+                .fixtures(fixtures)
+                .image_store(image_store.clone())
+                .srcset_widths(args.srcset_widths)
+                .build()?;
+
+            let source = AdoptapetSource::new(api, args.shelter_id);
+            let result = publish(&source, &options).await;
+            if let Some(image_store) = image_store {
+                // A failed run didn't see every listed pet's photos
+                if result.is_ok() {
+                    let pruned = image_store.prune();
+                    if pruned > 0 {
+                        println!("Dropped {} image records no longer in use", pruned);
+                    }
+                }
+                image_store.save();
+            }
+            result
+        }
+        SourceKind::Petfinder => {
+            let (Some(client_id), Some(client_secret)) =
+                (args.petfinder_client_id, args.petfinder_client_secret)
+            else {
+                bail!("--petfinder-client-id and --petfinder-client-secret are required");
+            };
+            if args.petfinder_organization.is_empty() {
+                bail!("--petfinder-organization is required");
+            }
+            if args.record.is_some() || args.replay.is_some() {
+                eprintln!("Warning: --record and --replay are ignored for Petfinder");
+            }
+            if args.cache_dir.is_some() {
+                eprintln!("Warning: --cache-dir only caches photo checks for Petfinder");
             }
-        })
-        .collect();
 
-    let pets_with_details = join_all(detail_futures).await;
+            let client =
+                build_http_client(connect_timeout, read_timeout, timeout, &args.user_agent)?;
+            let api = PetfinderApi::builder()
+                .base_url(args.petfinder_base_url)
+                .client_id(client_id)
+                .client_secret(client_secret)
+                .client(client)
+                .retry_policy(retry)
+                .concurrency_limits(limits)
+                .build();
 
-    // Fetch image metadata for all photos of each pet in parallel
-    println!("Fetching image metadata from Cloudinary...");
-    let metadata_futures: Vec<_> = pets_with_details
-        .into_iter()
-        .map(|(pet, details)| {
-            let api = &api;
-            async move {
-                let original_urls = pet.get_original_image_urls(details.as_ref());
-                let photos = api.get_all_image_metadata(original_urls).await;
-                (pet, details, photos)
-            }
-        })
-        .collect();
+            let source = PetfinderSource::new(api, args.petfinder_organization);
+            publish(&source, &options).await
+        }
+    }
+}
 
-    let pets_with_metadata = join_all(metadata_futures).await;
+/// Run a source through the pipeline and write the pets and failure summary.
+async fn publish<S: PetSource>(source: &S, options: &PublishOptions) -> Result<()> {
+    // Fetch the listing; a failed or empty listing must never be published
+    let listings = source.list_pets().await.context(ListingFailed)?;
+    println!(
+        "Fetched {} pets from {} listing",
+        listings.size(),
+        source.name()
+    );
+    // Pets that aren't listed anywhere (courtesy posts, animals in intake)
+    let extra_listings = match &options.extra_pets {
+        Some(extra) => extra.list_pets().await?,
+        None => Vec::new(),
+    };
+    if listings.is_empty() && extra_listings.is_empty() && !options.allow_empty {
+        return Err(anyhow!(
+            "No pets listed; pass --allow-empty to publish anyway"
+        ))
+        .context(ListingFailed);
+    }
 
-    // Convert to our output format
-    let pets: Vec<Pet> = pets_with_metadata
-        .into_iter()
-        .map(|(pet, details, photos)| pet.into_pet(details.as_ref(), photos))
-        .collect();
+    let mut failures = Vec::new();
+    let mut pets = collect_pets(source, listings, &mut failures).await;
+
+    if let Some(extra) = &options.extra_pets {
+        let extra_pets = collect_pets(extra, extra_listings, &mut failures).await;
+        let added = merge_extra_pets(&mut pets, extra_pets);
+        println!("Added {} pets from the extra pets file", added);
+    }
+
+    // Staff edits win over anything the sources say; photo_order counts
+    // photos as the source lists them
+    let today = Utc::now().date_naive();
+    if let Some(overrides) = &options.overrides {
+        pets = overrides.apply(pets, today, &options.media_base_url);
+    }
+
+    // Volunteers often upload the same photo twice
+    let duplicates = collapse_duplicate_photos(&mut pets);
+    if duplicates > 0 {
+        println!("Dropped {} duplicate photos", duplicates);
+    }
+
+    // A photo shared between pets is usually a litter photo
+    let shared = find_shared_photos(&pets);
+
+    // Lead with each pet's best photo, unless staff picked the photos
+    let fixed = options
+        .overrides
+        .as_ref()
+        .map(|overrides| overrides.fixed_photos(today))
+        .unwrap_or_default();
+    let fixed_photo_urls = options
+        .overrides
+        .as_ref()
+        .map(|overrides| overrides.fixed_photo_urls(today))
+        .unwrap_or_default();
+    let selection = select_primary_photos(
+        &mut pets,
+        options.photo_policy,
+        &shared.urls,
+        &fixed,
+        &options.media_base_url,
+    );
+    if !selection.is_empty() {
+        println!(
+            "Dropped {} photos below {}x{}; picked a better primary photo for {} pets",
+            selection.dropped,
+            options.photo_policy.min_width,
+            options.photo_policy.min_height,
+            selection.promoted
+        );
+    }
+
+    for group in &shared.groups {
+        let names: Vec<String> = group
+            .iter()
+            .map(|&i| format!("{} ({})", pets[i].name, pets[i].id))
+            .collect();
+        println!(
+            "Pets sharing photos (likely littermates): {}",
+            names.join(", ")
+        );
+    }
+    if options.link_littermates {
+        link_littermates(&mut pets, &shared.groups);
+    }
+
+    // Point the output at our own copies of the photos
+    if let Some(mirror) = &options.mirror {
+        failures.extend(mirror.mirror_pets(&mut pets, &fixed_photo_urls).await);
+    }
+
+    // The card's placeholder follows whichever photo ended up first, and
+    // every photo size follows the card photo
+    for pet in &mut pets {
+        pet.sync_primary_placeholders(&options.media_base_url);
+        options
+            .presets
+            .apply(pet, fixed_photo_urls.contains(pet.id.as_str()));
+    }
+
+    // Make sure every photo we publish actually loads
+    if let Some(checker) = &options.photo_checker {
+        let broken = checker.check_pets(&mut pets, &options.presets).await?;
+        let mut affected: Vec<String> = broken
+            .iter()
+            .map(|f| format!("{} ({})", f.pet_name, f.pet_id))
+            .collect();
+        affected.dedup();
+        if !affected.is_empty() {
+            println!(
+                "Pets with broken photos (re-upload needed): {}",
+                affected.join(", ")
+            );
+        }
+        failures.extend(broken);
+    }
 
     // Count pets with photos
     let mut pets_without_photos = Vec::new();
@@ -107,10 +649,34 @@ async fn main() -> Result<()> {
     let other = pets.len() - dogs - cats;
     println!("Breakdown: {} dogs, {} cats, {} other", dogs, cats, other);
 
+    // Count by shelter
+    let mut by_shelter: BTreeMap<&str, usize> = BTreeMap::new();
+    for pet in &pets {
+        if let Some(shelter_id) = &pet.shelter_id {
+            *by_shelter.entry(shelter_id).or_default() += 1;
+        }
+    }
+    for (shelter_id, count) in by_shelter {
+        println!("Shelter {}: {} pets", shelter_id, count);
+    }
+
+    // Report failed requests
+    for failure in &failures {
+        println!(
+            "Failed to fetch {} for {} ({}): {}",
+            failure.stage, failure.pet_name, failure.pet_id, failure.message
+        );
+    }
+    println!("{} requests failed", failures.len());
+
     // Create output data with timestamp
     let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
     let data = PetsData {
         pets,
+        updated_at: timestamp.clone(),
+    };
+    let failure_summary = FailureSummary {
+        failures,
         updated_at: timestamp,
     };
 
@@ -118,18 +684,45 @@ async fn main() -> Result<()> {
     let json_output = serde_json::to_string_pretty(&data)?;
 
     // Ensure output directory exists
-    if let Some(parent) = args.output.parent() {
+    let output = options.output.as_path();
+    if let Some(parent) = output.parent() {
         fs::create_dir_all(parent)?;
     }
 
     // Write to file
-    fs::write(&args.output, json_output)?;
+    fs::write(output, json_output)?;
 
-    println!("Wrote {} pets to {:?}", data.pets.len(), args.output);
+    println!("Wrote {} pets to {:?}", data.pets.len(), output);
+
+    // Write the failure summary next to the output
+    let failures_path = sibling_path(output, "failures");
+    fs::write(
+        &failures_path,
+        serde_json::to_string_pretty(&failure_summary)?,
+    )?;
+    println!(
+        "Wrote {} failures to {:?}",
+        failure_summary.failures.len(),
+        failures_path
+    );
+
+    // Nothing published points at photos this run didn't mirror any more
+    if let Some(mirror) = &options.mirror {
+        mirror.prune();
+    }
 
     Ok(())
 }
 
+/// Path of a file kept next to the output: (data/pets.json, "failures") -> data/pets.failures.json
+fn sibling_path(output: &Path, kind: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "pets".to_string());
+    output.with_file_name(format!("{}.{}.json", stem, kind))
+}
+
 // Extension trait to match Kotlin's size() method name
 trait VecExt {
     fn size(&self) -> usize;
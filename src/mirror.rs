@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use futures::future::join_all;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::cloudinary::CloudinaryUrl;
+use crate::error::ApiError;
+use crate::limits::{ConcurrencyLimits, Host};
+use crate::models::{
+    build_cloudinary_source_url, Pet, PhotoMetadata, PhotoRendition, PhotoVariant, RunFailure,
+};
+use crate::retry::RetryPolicy;
+
+/// Default widths, in pixels, of the resized copies made of each mirrored photo.
+pub const DEFAULT_MIRROR_WIDTHS: &str = "400,800,1200";
+
+/// Width the site's pet cards are designed for; `photoUrl` uses the variant closest to it.
+const CARD_WIDTH: u32 = 800;
+
+/// Quality of the resized variants, on each encoder's 0-100 scale.
+const JPEG_QUALITY: u8 = 82;
+const WEBP_QUALITY: f32 = 80.0;
+
+/// Endpoint name used in errors from downloading photos.
+const ENDPOINT: &str = "mirror";
+
+/// Where mirrored photos are written and how the site serves them.
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+    /// Directory the photos are written to
+    pub dir: PathBuf,
+    /// URL the site serves `dir` from, e.g. "/photos"
+    pub base_url: String,
+    /// Widths of the resized variants; none is ever wider than the original
+    pub widths: Vec<u32>,
+    /// Cloudinary host that Adoptapet photos are downloaded from
+    pub media_base_url: String,
+}
+
+/// A photo stored in the mirror, with paths relative to the mirror directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MirroredImage {
+    /// SHA-256 of the original bytes
+    hash: String,
+    original: String,
+    width: u32,
+    height: u32,
+    variants: Vec<MirroredVariant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MirroredVariant {
+    path: String,
+    width: u32,
+    height: u32,
+    format: String,
+}
+
+/// Source URL -> mirrored image, kept in the mirror directory so unchanged
+/// photos are not downloaded again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    images: BTreeMap<String, MirroredImage>,
+}
+
+/// Why a photo could not be mirrored.
+#[derive(Debug, thiserror::Error)]
+pub enum MirrorError {
+    #[error(transparent)]
+    Download(#[from] ApiError),
+    #[error("could not decode image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("could not write {path:?}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl MirrorError {
+    /// Short machine-readable kind for the failure summary.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MirrorError::Download(e) => e.kind(),
+            MirrorError::Decode(_) => "image_decode",
+            MirrorError::Write { .. } => "write",
+        }
+    }
+}
+
+/// Downloads every photo, stores it under a content-addressed path with resized
+/// WebP and JPEG variants, and points the pets at the mirrored files.
+pub struct PhotoMirror {
+    config: MirrorConfig,
+    client: Client,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    manifest: Mutex<Manifest>,
+    /// Source URLs mirrored (or found in the manifest) during this run
+    used: Mutex<HashSet<String>>,
+}
+
+impl PhotoMirror {
+    pub fn new(
+        config: MirrorConfig,
+        client: Client,
+        retry: RetryPolicy,
+        limits: ConcurrencyLimits,
+    ) -> Self {
+        let manifest = fs::read_to_string(manifest_path(&config.dir))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            config,
+            client,
+            retry,
+            limits,
+            manifest: Mutex::new(manifest),
+            used: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Mirror every pet's photos and rewrite `photoUrl` and `photos` to the mirrored
+    /// files. Photos that can't be mirrored keep their original URLs and are reported.
+    /// Pets in `fixed_photo_urls` keep the `photoUrl` staff set for them.
+    pub async fn mirror_pets(
+        &self,
+        pets: &mut [Pet],
+        fixed_photo_urls: &HashSet<&str>,
+    ) -> Vec<RunFailure> {
+        let urls: BTreeSet<&str> = pets
+            .iter()
+            .flat_map(|pet| pet.photos.iter().map(|p| p.original_url.as_str()))
+            .collect();
+        println!("Mirroring {} photos...", urls.len());
+        self.used
+            .lock()
+            .unwrap()
+            .extend(urls.iter().map(|url| url.to_string()));
+
+        let results: HashMap<String, Result<MirroredImage, MirrorError>> = join_all(
+            urls.into_iter()
+                .map(|url| async move { (url.to_string(), self.mirror_image(url).await) }),
+        )
+        .await
+        .into_iter()
+        .collect();
+
+        let mut failures = Vec::new();
+        for pet in pets.iter_mut() {
+            for photo in &mut pet.photos {
+                match &results[&photo.original_url] {
+                    Ok(image) => self.rewrite_photo(photo, image),
+                    Err(e) => failures.push(RunFailure {
+                        stage: "mirror".to_string(),
+                        pet_id: pet.id.clone(),
+                        pet_name: pet.name.clone(),
+                        kind: e.kind().to_string(),
+                        message: e.to_string(),
+                    }),
+                }
+            }
+            if fixed_photo_urls.contains(pet.id.as_str()) {
+                continue;
+            }
+            if let Some(url) = pet.photos.first().and_then(card_variant_url) {
+                pet.photo_url = Some(url);
+            }
+        }
+
+        self.save_manifest();
+        failures
+    }
+
+    /// Forget photos no pet used this run and delete their files. Only call
+    /// this once the output pointing at the mirror has been written.
+    pub fn prune(&self) {
+        let kept: HashSet<String> = {
+            let used = self.used.lock().unwrap();
+            let mut manifest = self.manifest.lock().unwrap();
+            manifest.images.retain(|url, _| used.contains(url));
+            manifest
+                .images
+                .values()
+                .map(|image| image.hash.clone())
+                .collect()
+        };
+
+        // Images live in <hash[..2]>/<hash>/; anything else in the directory is left alone
+        let mut removed = 0;
+        for prefix in subdirectories(&self.config.dir) {
+            let prefix_name = file_name(&prefix);
+            if prefix_name.len() != 2 {
+                continue;
+            }
+            for image_dir in subdirectories(&prefix) {
+                let hash = file_name(&image_dir);
+                if !is_sha256(&hash) || !hash.starts_with(&prefix_name) || kept.contains(&hash) {
+                    continue;
+                }
+                match fs::remove_dir_all(&image_dir) {
+                    Ok(()) => removed += 1,
+                    Err(e) => eprintln!(
+                        "Warning: could not remove mirrored photo {:?}: {}",
+                        image_dir, e
+                    ),
+                }
+            }
+            // Fails, harmlessly, while the prefix still holds images
+            let _ = fs::remove_dir(&prefix);
+        }
+
+        if removed > 0 {
+            println!("Removed {} mirrored photos no longer in use", removed);
+        }
+        self.save_manifest();
+    }
+
+    /// Mirror one photo, unless the manifest says it already is.
+    async fn mirror_image(&self, url: &str) -> Result<MirroredImage, MirrorError> {
+        let known = self.manifest.lock().unwrap().images.get(url).cloned();
+        if let Some(image) = known.filter(|image| self.is_complete(image)) {
+            return Ok(image);
+        }
+
+        // Adoptapet's URLs are a compressed rendition; start from the uploaded image
+        let source_url = match CloudinaryUrl::parse_on(url, &self.config.media_base_url) {
+            Ok(image) => build_cloudinary_source_url(&image, &self.config.media_base_url),
+            Err(_) => url.to_string(),
+        };
+        let bytes = self.download(&source_url).await?;
+        let dir = self.config.dir.clone();
+        let widths = self.config.widths.clone();
+        let image = tokio::task::spawn_blocking(move || write_image(&dir, &bytes, &widths))
+            .await
+            .expect("mirroring task panicked")?;
+
+        self.manifest
+            .lock()
+            .unwrap()
+            .images
+            .insert(url.to_string(), image.clone());
+        Ok(image)
+    }
+
+    /// Whether every file of a mirrored image exists and its variants match the configured widths.
+    fn is_complete(&self, image: &MirroredImage) -> bool {
+        let expected = variant_widths(&self.config.widths, image.width);
+        let actual: BTreeSet<u32> = image.variants.iter().map(|v| v.width).collect();
+        actual == expected
+            && std::iter::once(&image.original)
+                .chain(image.variants.iter().map(|v| &v.path))
+                .all(|path| self.config.dir.join(path).exists())
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>, ApiError> {
+        let _permit = self.limits.acquire(Host::Media).await;
+        let response = self
+            .retry
+            .send(|| self.client.get(url))
+            .await
+            .map_err(|e| ApiError::transport(ENDPOINT, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::http_status(ENDPOINT, status, ""));
+        }
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ApiError::transport(ENDPOINT, e))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Point a photo at its mirrored original and list its variants.
+    /// The JPEG variants replace any CDN renditions in `srcset`.
+    fn rewrite_photo(&self, photo: &mut PhotoMetadata, image: &MirroredImage) {
+        photo.original_url = self.public_url(&image.original);
+        photo.variants = image
+            .variants
+            .iter()
+            .map(|v| PhotoVariant {
+                url: self.public_url(&v.path),
+                width: v.width,
+                height: v.height,
+                format: v.format.clone(),
+            })
+            .collect();
+        let renditions = photo
+            .variants
+            .iter()
+            .filter(|v| v.format == "jpeg")
+            .map(|v| PhotoRendition {
+                url: v.url.clone(),
+                width: v.width,
+                height: v.height,
+            })
+            .collect();
+        photo.set_renditions(renditions);
+    }
+
+    fn public_url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    fn save_manifest(&self) {
+        let path = manifest_path(&self.config.dir);
+        let manifest = self.manifest.lock().unwrap();
+        let result = fs::create_dir_all(&self.config.dir).and_then(|_| {
+            fs::write(
+                &path,
+                serde_json::to_string_pretty(&*manifest).unwrap_or_default(),
+            )
+        });
+        if let Err(e) = result {
+            eprintln!("Warning: could not save mirror manifest {:?}: {}", path, e);
+        }
+    }
+}
+
+fn manifest_path(dir: &Path) -> PathBuf {
+    dir.join("manifest.json")
+}
+
+/// Subdirectories of a directory; none if it can't be read.
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_sha256(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The JPEG variant closest to the card width, for `photoUrl`.
+fn card_variant_url(photo: &PhotoMetadata) -> Option<String> {
+    photo
+        .variants
+        .iter()
+        .filter(|v| v.format == "jpeg")
+        .min_by_key(|v| v.width.abs_diff(CARD_WIDTH))
+        .map(|v| v.url.clone())
+}
+
+/// Variant widths for an image, capped at its own width so nothing is upscaled.
+fn variant_widths(widths: &[u32], image_width: u32) -> BTreeSet<u32> {
+    widths
+        .iter()
+        .map(|&width| width.min(image_width))
+        .filter(|&width| width > 0)
+        .collect()
+}
+
+/// Store the original under `<hash[..2]>/<hash>/` with a WebP and a JPEG per width.
+/// Files that already exist are left alone: the same hash means the same content.
+fn write_image(dir: &Path, bytes: &[u8], widths: &[u32]) -> Result<MirroredImage, MirrorError> {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+    let extension = image::guess_format(bytes)?
+        .extensions_str()
+        .first()
+        .copied()
+        .unwrap_or("img");
+    let image = image::load_from_memory(bytes)?;
+    let (width, height) = (image.width(), image.height());
+
+    let relative_dir = format!("{}/{}", &hash[..2], hash);
+    let absolute_dir = dir.join(&relative_dir);
+    fs::create_dir_all(&absolute_dir).map_err(|source| MirrorError::Write {
+        path: absolute_dir.clone(),
+        source,
+    })?;
+
+    let original = format!("{}/original.{}", relative_dir, extension);
+    let original_path = dir.join(&original);
+    if !original_path.exists() {
+        fs::write(&original_path, bytes).map_err(|source| MirrorError::Write {
+            path: original_path,
+            source,
+        })?;
+    }
+
+    let mut variants = Vec::new();
+    for variant_width in variant_widths(widths, width) {
+        let variant_height = ((height as u64 * variant_width as u64) / width as u64).max(1) as u32;
+        let resized = if variant_width == width {
+            image.clone()
+        } else {
+            image.resize_exact(variant_width, variant_height, FilterType::Lanczos3)
+        };
+
+        for format in ["webp", "jpeg"] {
+            let path = format!("{}/w{}.{}", relative_dir, variant_width, format);
+            encode_variant(&resized, &dir.join(&path), format)?;
+            variants.push(MirroredVariant {
+                path,
+                width: variant_width,
+                height: variant_height,
+                format: format.to_string(),
+            });
+        }
+    }
+
+    Ok(MirroredImage {
+        hash,
+        original,
+        width,
+        height,
+        variants,
+    })
+}
+
+fn encode_variant(image: &DynamicImage, path: &Path, format: &str) -> Result<(), MirrorError> {
+    if path.exists() {
+        return Ok(());
+    }
+    let write_error = |source| MirrorError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    let rgb = image.to_rgb8();
+
+    match format {
+        "webp" => {
+            let encoded =
+                webp::Encoder::from_rgb(&rgb, rgb.width(), rgb.height()).encode(WEBP_QUALITY);
+            fs::write(path, &*encoded).map_err(write_error)?;
+        }
+        _ => {
+            let writer = BufWriter::new(File::create(path).map_err(write_error)?);
+            DynamicImage::ImageRgb8(rgb)
+                .write_with_encoder(JpegEncoder::new_with_quality(writer, JPEG_QUALITY))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_variant_widths_never_upscale() {
+        let widths: Vec<_> = variant_widths(&[400, 800, 1200], 1000)
+            .into_iter()
+            .collect();
+        assert_eq!(widths, [400, 800, 1000]);
+    }
+
+    #[test]
+    fn test_write_image_is_content_addressed() {
+        let dir = std::env::temp_dir().join(format!("update-pets-mirror-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let bytes = jpeg(1000, 750);
+
+        let image = write_image(&dir, &bytes, &[400, 1200]).unwrap();
+
+        assert!(image
+            .original
+            .starts_with(&format!("{}/{}", &image.hash[..2], image.hash)));
+        assert!(image.original.ends_with("original.jpg"));
+        let variants: Vec<_> = image
+            .variants
+            .iter()
+            .map(|v| (v.width, v.height, v.format.as_str()))
+            .collect();
+        assert_eq!(
+            variants,
+            [
+                (400, 300, "webp"),
+                (400, 300, "jpeg"),
+                (1000, 750, "webp"),
+                (1000, 750, "jpeg"),
+            ]
+        );
+        for variant in &image.variants {
+            let decoded = image::open(dir.join(&variant.path)).unwrap();
+            assert_eq!(decoded.width(), variant.width);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_removes_unused_photos() {
+        let dir =
+            std::env::temp_dir().join(format!("update-pets-mirror-prune-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let kept = write_image(&dir, &jpeg(40, 30), &[20]).unwrap();
+        let gone = write_image(&dir, &jpeg(30, 40), &[20]).unwrap();
+        fs::write(dir.join("README"), "not ours").unwrap();
+
+        let mirror = PhotoMirror::new(
+            MirrorConfig {
+                dir: dir.clone(),
+                base_url: "/photos".to_string(),
+                widths: vec![20],
+                media_base_url: String::new(),
+            },
+            Client::new(),
+            RetryPolicy::default(),
+            ConcurrencyLimits::default(),
+        );
+        {
+            let mut manifest = mirror.manifest.lock().unwrap();
+            manifest.images.insert("kept".to_string(), kept.clone());
+            manifest.images.insert("gone".to_string(), gone.clone());
+        }
+        mirror.used.lock().unwrap().insert("kept".to_string());
+
+        mirror.prune();
+
+        assert!(dir.join(&kept.original).exists());
+        assert!(!dir.join(&gone.original).exists());
+        assert!(dir.join("README").exists());
+        let manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(manifest_path(&dir)).unwrap()).unwrap();
+        assert_eq!(manifest.images.keys().collect::<Vec<_>>(), ["kept"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
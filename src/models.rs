@@ -1,12 +1,48 @@
+use std::collections::BTreeSet;
+
 use htmd::HtmlToMarkdown;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::cloudinary::CloudinaryUrl;
+
 /// Response from the Adoptapet pets_at_shelter endpoint.
+/// Error responses (invalid key, suspended account) have no `pets` and
+/// usually carry a `status`/`error`/`message` instead.
 #[derive(Debug, Deserialize)]
 pub struct AdoptapetResponse {
-    #[serde(default)]
-    pub pets: Vec<AdoptapetPet>,
+    /// Missing past the last page, but also from error bodies of any shape
+    pub pets: Option<Vec<AdoptapetPet>>,
+    pub status: Option<String>,
+    pub error: Option<serde_json::Value>,
+    pub message: Option<String>,
+}
+
+impl AdoptapetResponse {
+    /// The API's error message, if this is an error response.
+    pub fn error_message(&self) -> Option<String> {
+        let error = match &self.error {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(error)) => Some(error.clone()),
+            Some(error) => Some(
+                error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| error.to_string()),
+            ),
+        };
+        if error.is_some() {
+            return error;
+        }
+
+        match (self.status.as_deref(), &self.message) {
+            (Some(status), _) if matches!(status.to_lowercase().as_str(), "ok" | "success") => None,
+            (_, Some(message)) => Some(message.clone()),
+            (Some(status), None) => Some(format!("status {}", status)),
+            (None, None) => None,
+        }
+    }
 }
 
 /// Pet data from the Adoptapet API (pets_at_shelter endpoint).
@@ -21,6 +57,9 @@ pub struct AdoptapetPet {
     pub sex: Option<String>,
     pub size: Option<String>,
     pub large_results_photo_url: Option<String>,
+    /// Shelter account whose listing this pet came from (set by us, not the API)
+    #[serde(skip)]
+    pub shelter_id: Option<String>,
 }
 
 /// Response from the Adoptapet pet_details endpoint.
@@ -83,6 +122,137 @@ pub struct PhotoMetadata {
     /// Aspect ratio of the original image (width / height)
     #[serde(rename = "aspectRatio")]
     pub aspect_ratio: f32,
+    /// Resized copies of the photo, when photos are mirrored locally
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<PhotoVariant>,
+    /// Renditions for responsive images, narrowest first; none wider than the original
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<PhotoRendition>,
+    /// The renditions as an `<img srcset>` value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srcset: Option<String>,
+    /// BlurHash to show while the photo loads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>,
+    /// Tiny blurry version of the photo as a data: URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lqip: Option<String>,
+    /// Most common color of the photo, as "#rrggbb"
+    #[serde(rename = "dominantColor", skip_serializing_if = "Option::is_none")]
+    pub dominant_color: Option<String>,
+    /// A few of the photo's most common colors, most common first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<String>,
+    /// Perceptual hash, for spotting the same photo uploaded twice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phash: Option<String>,
+    /// 1-based position in the source's photo list, counting photos whose
+    /// metadata failed; 0 when unknown
+    #[serde(skip)]
+    pub source_position: usize,
+}
+
+impl PhotoMetadata {
+    pub fn new(original_url: String, width: u32, height: u32) -> Self {
+        Self {
+            original_url,
+            width,
+            height,
+            aspect_ratio: width as f32 / height as f32,
+            variants: Vec::new(),
+            renditions: Vec::new(),
+            srcset: None,
+            blurhash: None,
+            lqip: None,
+            dominant_color: None,
+            palette: Vec::new(),
+            phash: None,
+            source_position: 0,
+        }
+    }
+
+    /// Whether `url` shows this photo: the photo itself, one of its sizes, or
+    /// another transformation of the same Cloudinary image.
+    pub fn is_shown_by(&self, url: &str, media_base_url: &str) -> bool {
+        if url == self.original_url
+            || self.variants.iter().any(|v| v.url == url)
+            || self.renditions.iter().any(|r| r.url == url)
+        {
+            return true;
+        }
+        match (
+            CloudinaryUrl::parse_on(url, media_base_url),
+            CloudinaryUrl::parse_on(&self.original_url, media_base_url),
+        ) {
+            (Ok(shown), Ok(photo)) => shown.public_id == photo.public_id,
+            _ => false,
+        }
+    }
+
+    /// Number of pixels in the original.
+    pub fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    /// Add Cloudinary renditions of the photo at the given widths, built on
+    /// `media_base_url`. Widths past the original are capped at it, so nothing
+    /// is upscaled.
+    pub fn with_cloudinary_renditions(
+        mut self,
+        image: &CloudinaryUrl,
+        widths: &[u32],
+        media_base_url: &str,
+    ) -> Self {
+        let widths: BTreeSet<u32> = widths
+            .iter()
+            .map(|&width| width.min(self.width))
+            .filter(|&width| width > 0)
+            .collect();
+        let renditions = widths
+            .into_iter()
+            .map(|width| PhotoRendition {
+                url: build_cloudinary_rendition_url(image, width, media_base_url),
+                width,
+                height: (width as f32 / self.aspect_ratio).round() as u32,
+            })
+            .collect();
+        self.set_renditions(renditions);
+        self
+    }
+
+    /// Replace the renditions, keeping `srcset` in step.
+    pub fn set_renditions(&mut self, renditions: Vec<PhotoRendition>) {
+        self.srcset = if renditions.is_empty() {
+            None
+        } else {
+            Some(
+                renditions
+                    .iter()
+                    .map(|r| format!("{} {}w", r.url, r.width))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        };
+        self.renditions = renditions;
+    }
+}
+
+/// A photo resized to one width, for `srcset`.
+#[derive(Debug, Serialize, Clone)]
+pub struct PhotoRendition {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A resized copy of a photo in one format.
+#[derive(Debug, Serialize, Clone)]
+pub struct PhotoVariant {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    /// File format ("webp" or "jpeg")
+    pub format: String,
 }
 
 /// A named attribute with display name. Only true attributes are included.
@@ -93,7 +263,7 @@ pub struct Attribute {
 }
 
 /// Simplified pet model for output JSON consumed by the website.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct Pet {
     pub id: String,
     pub name: String,
@@ -104,8 +274,29 @@ pub struct Pet {
     pub sex: Option<String>,
     pub size: Option<String>,
     pub url: String,
+    /// Shelter account the pet is listed under
+    #[serde(rename = "shelterId", skip_serializing_if = "Option::is_none")]
+    pub shelter_id: Option<String>,
     #[serde(rename = "photoUrl")]
     pub photo_url: Option<String>,
+    /// BlurHash of the primary photo
+    #[serde(rename = "photoBlurhash", skip_serializing_if = "Option::is_none")]
+    pub photo_blurhash: Option<String>,
+    /// Tiny blurry version of the primary photo as a data: URL
+    #[serde(rename = "photoLqip", skip_serializing_if = "Option::is_none")]
+    pub photo_lqip: Option<String>,
+    /// Most common color of the primary photo, for tinting the card
+    #[serde(rename = "photoDominantColor", skip_serializing_if = "Option::is_none")]
+    pub photo_dominant_color: Option<String>,
+    /// Primary photo with the thumbnail preset
+    #[serde(rename = "thumbnailUrl", skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    /// Primary photo with the hero preset
+    #[serde(rename = "heroUrl", skip_serializing_if = "Option::is_none")]
+    pub hero_url: Option<String>,
+    /// Primary photo with the social (link preview) preset
+    #[serde(rename = "socialUrl", skip_serializing_if = "Option::is_none")]
+    pub social_url: Option<String>,
     /// All photos with metadata (dimensions, aspect ratio, URL)
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub photos: Vec<PhotoMetadata>,
@@ -129,6 +320,9 @@ pub struct Pet {
     /// Compatibility and status attributes
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub attributes: Vec<Attribute>,
+    /// IDs of other pets sharing a photo with this one, likely littermates
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub littermates: Vec<String>,
 }
 
 /// Wrapper for the output JSON.
@@ -139,68 +333,134 @@ pub struct PetsData {
     pub updated_at: String,
 }
 
-/// Extract the Cloudinary image ID from the original_url and build a high-res URL.
-/// Input: https://media.adoptapet.com/image/upload/.../1268757503
-/// Output: https://media.adoptapet.com/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/1268757503
-pub fn extract_high_res_image_url(original_url: Option<&str>) -> Option<String> {
-    let url = original_url?.trim();
-    if url.is_empty() {
-        return None;
-    }
+/// A request that failed during a run, for the failure summary.
+#[derive(Debug, Serialize)]
+pub struct RunFailure {
+    /// Pipeline stage that failed ("details" or "photo")
+    pub stage: String,
+    #[serde(rename = "petId")]
+    pub pet_id: String,
+    #[serde(rename = "petName")]
+    pub pet_name: String,
+    /// Failure kind (transport, http_status, decode, missing_data)
+    pub kind: String,
+    pub message: String,
+}
 
-    // Extract the image ID (last path segment, no extension)
-    let image_id = url.rsplit('/').next()?.split('.').next()?;
+/// Wrapper for the failure summary JSON written alongside the pets output.
+#[derive(Debug, Serialize)]
+pub struct FailureSummary {
+    pub failures: Vec<RunFailure>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
 
-    if image_id.is_empty() {
-        return Some(url.to_string());
-    }
+/// Public Cloudinary host serving Adoptapet photos.
+pub const MEDIA_BASE_URL: &str = "https://media.adoptapet.com";
 
-    // Return optimized URL with reasonable size (800x600, 4:3 aspect)
-    Some(format!(
-        "https://media.adoptapet.com/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/{}",
-        image_id
+/// Default widths, in pixels, of the Cloudinary renditions listed in each photo's `srcset`.
+pub const DEFAULT_SRCSET_WIDTHS: &str = "320,640,960,1280,1920";
+
+/// Default Cloudinary transformation for the card photo (800x600, 4:3 aspect).
+pub const DEFAULT_CARD_PRESET: &str = "c_fill,w_800,h_600,g_auto/f_auto,q_auto";
+
+/// Build a high-res URL from the original_url, with the default card preset.
+/// Configured presets are applied later in the run. None unless the URL is a
+/// Cloudinary URL on Adoptapet's or the configured media host.
+/// Input: https://media.adoptapet.com/image/upload/.../1268757503
+/// Output: {media_base_url}/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/1268757503
+pub fn extract_high_res_image_url(
+    original_url: Option<&str>,
+    media_base_url: &str,
+) -> Option<String> {
+    let image = CloudinaryUrl::parse_on(original_url?, media_base_url).ok()?;
+    Some(build_cloudinary_preset_url(
+        &image,
+        DEFAULT_CARD_PRESET,
+        media_base_url,
     ))
 }
 
-/// Extract the Cloudinary image ID from the original_url.
+/// Build the URL of an image with a named preset's transformation.
+/// Input: .../1268757503, c_fill,w_200,h_200,g_auto/f_auto,q_auto
+/// Output: {media_base_url}/image/upload/c_fill,w_200,h_200,g_auto/f_auto,q_auto/1268757503
+pub fn build_cloudinary_preset_url(
+    image: &CloudinaryUrl,
+    transformation: &str,
+    media_base_url: &str,
+) -> String {
+    image
+        .with_transformation(transformation)
+        .to_url(media_base_url)
+}
+
+/// Build a Cloudinary fl_getinfo URL to fetch image metadata.
 /// Input: https://media.adoptapet.com/image/upload/.../1268757503
-/// Returns: 1268757503
-pub fn extract_cloudinary_image_id(original_url: &str) -> Option<&str> {
-    let url = original_url.trim();
-    if url.is_empty() {
-        return None;
-    }
+/// Output: {media_base_url}/image/upload/fl_getinfo/1268757503
+pub fn build_cloudinary_info_url(image: &CloudinaryUrl, media_base_url: &str) -> String {
+    image
+        .with_transformation("fl_getinfo")
+        .to_url(media_base_url)
+}
 
-    // Extract the image ID (last path segment, no extension)
-    let image_id = url.rsplit('/').next()?.split('.').next()?;
+/// Build the URL of the stored image itself (no transformations, original format).
+/// Input: .../1268757503
+/// Output: {media_base_url}/image/upload/1268757503
+pub fn build_cloudinary_source_url(image: &CloudinaryUrl, media_base_url: &str) -> String {
+    image.with_transformation("").to_url(media_base_url)
+}
 
-    if image_id.is_empty() {
-        None
-    } else {
-        Some(image_id)
-    }
+/// Build the URL of a tiny JPEG rendition, for placeholders and perceptual hashes.
+/// Input: .../1268757503
+/// Output: {media_base_url}/image/upload/c_limit,w_32,h_32/f_jpg,q_60/1268757503
+pub fn build_cloudinary_preview_url(image: &CloudinaryUrl, media_base_url: &str) -> String {
+    image
+        .with_transformation("c_limit,w_32,h_32/f_jpg,q_60")
+        .to_url(media_base_url)
 }
 
-/// Build a Cloudinary fl_getinfo URL to fetch image metadata.
-/// Input: https://media.adoptapet.com/image/upload/.../1268757503
-/// Output: https://media.adoptapet.com/image/upload/fl_getinfo/1268757503
-pub fn build_cloudinary_info_url(original_url: &str) -> Option<String> {
-    let image_id = extract_cloudinary_image_id(original_url)?;
-    Some(format!(
-        "https://media.adoptapet.com/image/upload/fl_getinfo/{}",
-        image_id
-    ))
+/// Build the URL of a rendition no wider than `width`, for `srcset`.
+/// Input: .../1268757503, 640
+/// Output: {media_base_url}/image/upload/c_limit,w_640/f_auto,q_auto/1268757503
+pub fn build_cloudinary_rendition_url(
+    image: &CloudinaryUrl,
+    width: u32,
+    media_base_url: &str,
+) -> String {
+    image
+        .with_transformation(&format!("c_limit,w_{}/f_auto,q_auto", width))
+        .to_url(media_base_url)
 }
 
 /// Build the original Cloudinary URL (no transformations, just format optimization).
 /// Input: https://media.adoptapet.com/image/upload/.../1268757503
-/// Output: https://media.adoptapet.com/image/upload/f_auto,q_auto/1268757503
-pub fn build_cloudinary_original_url(original_url: &str) -> Option<String> {
-    let image_id = extract_cloudinary_image_id(original_url)?;
-    Some(format!(
-        "https://media.adoptapet.com/image/upload/f_auto,q_auto/{}",
-        image_id
-    ))
+/// Output: {media_base_url}/image/upload/f_auto,q_auto/1268757503
+pub fn build_cloudinary_original_url(image: &CloudinaryUrl, media_base_url: &str) -> String {
+    image
+        .with_transformation("f_auto,q_auto")
+        .to_url(media_base_url)
+}
+
+impl Pet {
+    /// Copy the placeholders of the photo the card shows onto the pet. Call this
+    /// before presets rewrite `photo_url`. A card photo that isn't in the
+    /// gallery (set by staff) gets no placeholders.
+    pub fn sync_primary_placeholders(&mut self, media_base_url: &str) {
+        let primary = self.photo_url.as_deref().and_then(|url| {
+            self.photos
+                .iter()
+                .find(|photo| photo.is_shown_by(url, media_base_url))
+        });
+        self.photo_blurhash = primary.and_then(|p| p.blurhash.clone());
+        self.photo_lqip = primary.and_then(|p| p.lqip.clone());
+        self.photo_dominant_color = primary.and_then(|p| p.dominant_color.clone());
+    }
+}
+
+/// The card URL for a photo: Cloudinary photos get the card crop, others are used as is.
+pub fn card_photo_url(original_url: &str, media_base_url: &str) -> String {
+    extract_high_res_image_url(Some(original_url), media_base_url)
+        .unwrap_or_else(|| original_url.to_string())
 }
 
 impl AdoptapetPet {
@@ -220,12 +480,18 @@ impl AdoptapetPet {
 
     /// Convert Adoptapet pet + details + photo metadata to our simplified model.
     /// Consumes self to avoid cloning strings.
-    pub fn into_pet(self, details: Option<&PetDetails>, photos: Vec<PhotoMetadata>) -> Pet {
+    /// The card photo is built on `media_base_url`.
+    pub fn into_pet(
+        self,
+        details: Option<&PetDetails>,
+        photos: Vec<PhotoMetadata>,
+        media_base_url: &str,
+    ) -> Pet {
         // Get high-res photo from details, fall back to low-res from listing
         // Filter out "/null" placeholder URLs
-        let high_res_photo = details
-            .and_then(|d| d.images.first())
-            .and_then(|img| extract_high_res_image_url(img.original_url.as_deref()));
+        let high_res_photo = details.and_then(|d| d.images.first()).and_then(|img| {
+            extract_high_res_image_url(img.original_url.as_deref(), media_base_url)
+        });
 
         let final_photo_url = high_res_photo
             .or(self.large_results_photo_url)
@@ -238,15 +504,10 @@ impl AdoptapetPet {
             None => "Other".to_string(),
         };
 
-        // Combine primary and secondary breed, excluding "Unknown Type" entries
-        let breed = [&self.primary_breed, &self.secondary_breed]
-            .iter()
-            .filter_map(|b| b.as_ref())
-            .filter(|b| !b.trim().is_empty() && !b.contains("Unknown Type"))
-            .map(|s| s.as_str())
-            .collect::<Vec<_>>()
-            .join(" / ");
-        let breed = if breed.is_empty() { None } else { Some(breed) };
+        let breed = combine_breeds(
+            self.primary_breed.as_deref(),
+            self.secondary_breed.as_deref(),
+        );
 
         // Convert sex abbreviation to full word, None if missing
         let sex = match self.sex.as_deref().map(|s| s.to_lowercase()).as_deref() {
@@ -263,24 +524,7 @@ impl AdoptapetPet {
         let size = self.size.clone();
 
         // Process description in multiple formats
-        let raw_description = details.and_then(|d| d.description.as_ref());
-        let description = raw_description.map(|desc| clean_html_description(desc));
-        let description_html = raw_description.map(|desc| sanitize_html_description(desc));
-        let description_markdown = raw_description.and_then(|desc| html_to_markdown(desc));
-
-        // Extract short description: text before "Please email" or truncate to 200 chars
-        let short_description = description.as_ref().map(|desc| {
-            if let Some(idx) = desc.to_lowercase().find("please email") {
-                if idx > 0 {
-                    return desc[..idx].trim().to_string();
-                }
-            }
-            if desc.len() > 200 {
-                format!("{}...", desc[..200].trim())
-            } else {
-                desc.clone()
-            }
-        });
+        let descriptions = Descriptions::from_html(details.and_then(|d| d.description.as_deref()));
 
         let url = details
             .and_then(|d| d.pet_details_url.clone())
@@ -299,20 +543,83 @@ impl AdoptapetPet {
             sex,
             size,
             url,
+            shelter_id: self.shelter_id,
             photo_url: final_photo_url,
+            photo_blurhash: None,
+            photo_lqip: None,
+            photo_dominant_color: None,
+            thumbnail_url: None,
+            hero_url: None,
+            social_url: None,
             photos,
-            description,
-            description_html,
-            description_markdown,
-            short_description,
+            description: descriptions.text,
+            description_html: descriptions.html,
+            description_markdown: descriptions.markdown,
+            short_description: descriptions.short,
             color,
             attributes,
+            littermates: Vec::new(),
+        }
+    }
+}
+
+/// A pet description in every format the website uses.
+#[derive(Debug, Default)]
+pub struct Descriptions {
+    /// Plain text (HTML stripped)
+    pub text: Option<String>,
+    /// Original HTML (reference codes removed)
+    pub html: Option<String>,
+    pub markdown: Option<String>,
+    /// Text before "Please email", or the first 200 characters
+    pub short: Option<String>,
+}
+
+impl Descriptions {
+    /// Derive every format from a raw HTML description.
+    pub fn from_html(raw: Option<&str>) -> Self {
+        let Some(raw) = raw else {
+            return Self::default();
+        };
+
+        let text = clean_html_description(raw);
+
+        // Extract short description: text before "Please email" or truncate to 200 chars
+        let short = match text.to_lowercase().find("please email") {
+            Some(idx) if idx > 0 => text[..idx].trim().to_string(),
+            // Cut on a character boundary; slicing bytes panics on accented text
+            _ => match text.char_indices().nth(200) {
+                Some((idx, _)) => format!("{}...", text[..idx].trim()),
+                None => text.clone(),
+            },
+        };
+
+        Self {
+            html: Some(sanitize_html_description(raw)),
+            markdown: html_to_markdown(raw),
+            short: Some(short),
+            text: Some(text),
         }
     }
 }
 
+/// Combine primary and secondary breed, excluding empty and "Unknown Type" entries.
+pub fn combine_breeds(primary: Option<&str>, secondary: Option<&str>) -> Option<String> {
+    let breed = [primary, secondary]
+        .into_iter()
+        .flatten()
+        .filter(|b| !b.trim().is_empty() && !b.contains("Unknown Type"))
+        .collect::<Vec<_>>()
+        .join(" / ");
+    if breed.is_empty() {
+        None
+    } else {
+        Some(breed)
+    }
+}
+
 /// Capitalize the first character of a string.
-fn capitalize_first(s: &str) -> String {
+pub fn capitalize_first(s: &str) -> String {
     let mut chars = s.chars();
     match chars.next() {
         None => String::new(),
@@ -368,64 +675,59 @@ fn api_bool(value: Option<u8>) -> Option<bool> {
     value.map(|v| v == 1)
 }
 
+/// Yes/no facts about a pet, shared by every source. None means unknown.
+#[derive(Debug, Default, Clone)]
+pub struct AttributeFlags {
+    pub good_with_cats: Option<bool>,
+    pub good_with_dogs: Option<bool>,
+    pub good_with_kids: Option<bool>,
+    pub housetrained: Option<bool>,
+    pub shots_current: Option<bool>,
+    pub spayed_neutered: Option<bool>,
+    pub special_needs: Option<bool>,
+    pub declawed: Option<bool>,
+}
+
 /// Build attributes list from pet details. Only includes true attributes.
 fn build_attributes(details: Option<&PetDetails>) -> Vec<Attribute> {
     let Some(d) = details else {
         return Vec::new();
     };
 
-    let mut attrs = Vec::new();
-
-    if api_bool(d.good_with_cats) == Some(true) {
-        attrs.push(Attribute {
-            key: "good_with_cats".to_string(),
-            display: "Good with cats".to_string(),
-        });
-    }
-    if api_bool(d.good_with_dogs) == Some(true) {
-        attrs.push(Attribute {
-            key: "good_with_dogs".to_string(),
-            display: "Good with dogs".to_string(),
-        });
-    }
-    if api_bool(d.good_with_kids) == Some(true) {
-        attrs.push(Attribute {
-            key: "good_with_kids".to_string(),
-            display: "Good with kids".to_string(),
-        });
-    }
-    if api_bool(d.housetrained) == Some(true) {
-        attrs.push(Attribute {
-            key: "housetrained".to_string(),
-            display: "Housetrained".to_string(),
-        });
-    }
-    if api_bool(d.shots_current) == Some(true) {
-        attrs.push(Attribute {
-            key: "shots_current".to_string(),
-            display: "Shots current".to_string(),
-        });
-    }
-    if api_bool(d.spayed_neutered) == Some(true) {
-        attrs.push(Attribute {
-            key: "spayed_neutered".to_string(),
-            display: "Spayed/Neutered".to_string(),
-        });
-    }
-    if api_bool(d.special_needs) == Some(true) {
-        attrs.push(Attribute {
-            key: "special_needs".to_string(),
-            display: "Special needs".to_string(),
-        });
-    }
-    if api_bool(d.declawed) == Some(true) {
-        attrs.push(Attribute {
-            key: "declawed".to_string(),
-            display: "Declawed".to_string(),
-        });
+    AttributeFlags {
+        good_with_cats: api_bool(d.good_with_cats),
+        good_with_dogs: api_bool(d.good_with_dogs),
+        good_with_kids: api_bool(d.good_with_kids),
+        housetrained: api_bool(d.housetrained),
+        shots_current: api_bool(d.shots_current),
+        spayed_neutered: api_bool(d.spayed_neutered),
+        special_needs: api_bool(d.special_needs),
+        declawed: api_bool(d.declawed),
     }
+    .to_attributes()
+}
 
-    attrs
+impl AttributeFlags {
+    /// Build the attributes list. Only includes true attributes.
+    pub fn to_attributes(&self) -> Vec<Attribute> {
+        [
+            (self.good_with_cats, "good_with_cats", "Good with cats"),
+            (self.good_with_dogs, "good_with_dogs", "Good with dogs"),
+            (self.good_with_kids, "good_with_kids", "Good with kids"),
+            (self.housetrained, "housetrained", "Housetrained"),
+            (self.shots_current, "shots_current", "Shots current"),
+            (self.spayed_neutered, "spayed_neutered", "Spayed/Neutered"),
+            (self.special_needs, "special_needs", "Special needs"),
+            (self.declawed, "declawed", "Declawed"),
+        ]
+        .into_iter()
+        .filter(|(flag, _, _)| *flag == Some(true))
+        .map(|(_, key, display)| Attribute {
+            key: key.to_string(),
+            display: display.to_string(),
+        })
+        .collect()
+    }
 }
 
 #[cfg(test)]
@@ -435,17 +737,118 @@ mod tests {
     #[test]
     fn test_extract_high_res_url() {
         let url = "https://media.adoptapet.com/image/upload/v123/1268757503";
-        let result = extract_high_res_image_url(Some(url));
+        let result = extract_high_res_image_url(Some(url), MEDIA_BASE_URL);
         assert_eq!(
             result,
-            Some("https://media.adoptapet.com/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/1268757503".to_string())
+            Some("https://media.adoptapet.com/image/upload/c_fill,w_800,h_600,g_auto/f_auto,q_auto/v123/1268757503".to_string())
         );
     }
 
     #[test]
     fn test_extract_high_res_url_none() {
-        assert_eq!(extract_high_res_image_url(None), None);
-        assert_eq!(extract_high_res_image_url(Some("")), None);
+        assert_eq!(extract_high_res_image_url(None, MEDIA_BASE_URL), None);
+        assert_eq!(extract_high_res_image_url(Some(""), MEDIA_BASE_URL), None);
+        assert_eq!(
+            extract_high_res_image_url(
+                Some("https://example.com/image/upload/1268757503"),
+                MEDIA_BASE_URL
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cloudinary_renditions_never_upscale() {
+        let photo = PhotoMetadata::new("original".to_string(), 1000, 750)
+            .with_cloudinary_renditions(
+                &CloudinaryUrl::parse_on(
+                    "https://media.adoptapet.com/image/upload/1268757503",
+                    MEDIA_BASE_URL,
+                )
+                .unwrap(),
+                &[640, 320, 1280, 1920],
+                MEDIA_BASE_URL,
+            );
+
+        let widths: Vec<_> = photo
+            .renditions
+            .iter()
+            .map(|r| (r.width, r.height))
+            .collect();
+        assert_eq!(widths, [(320, 240), (640, 480), (1000, 750)]);
+        assert_eq!(
+            photo.srcset.as_deref(),
+            Some(
+                "https://media.adoptapet.com/image/upload/c_limit,w_320/f_auto,q_auto/1268757503 320w, \
+                 https://media.adoptapet.com/image/upload/c_limit,w_640/f_auto,q_auto/1268757503 640w, \
+                 https://media.adoptapet.com/image/upload/c_limit,w_1000/f_auto,q_auto/1268757503 1000w"
+            )
+        );
+    }
+
+    #[test]
+    fn test_placeholders_follow_the_card_photo() {
+        let photo = |id: &str, blurhash: &str| PhotoMetadata {
+            blurhash: Some(blurhash.to_string()),
+            ..PhotoMetadata::new(
+                format!("{}/image/upload/f_auto,q_auto/{}", MEDIA_BASE_URL, id),
+                800,
+                600,
+            )
+        };
+        let mut pet = Pet {
+            photo_url: Some(card_photo_url(
+                &format!("{}/image/upload/2", MEDIA_BASE_URL),
+                MEDIA_BASE_URL,
+            )),
+            photos: vec![photo("1", "first"), photo("2", "second")],
+            ..Default::default()
+        };
+
+        pet.sync_primary_placeholders(MEDIA_BASE_URL);
+        assert_eq!(pet.photo_blurhash.as_deref(), Some("second"));
+
+        // A card photo staff picked from outside the gallery has none
+        pet.photo_url = Some("https://example.org/staff-pick.jpg".to_string());
+        pet.sync_primary_placeholders(MEDIA_BASE_URL);
+        assert_eq!(pet.photo_blurhash, None);
+    }
+
+    #[test]
+    fn test_adoptapet_error_message() {
+        let parse = |json: &str| serde_json::from_str::<AdoptapetResponse>(json).unwrap();
+
+        assert_eq!(parse(r#"{"pets": []}"#).error_message(), None);
+        // Past the last page the API leaves out `pets` without any error
+        assert!(parse("{}").pets.is_none());
+        assert_eq!(parse("{}").error_message(), None);
+        assert_eq!(
+            parse(r#"{"message": "Invalid shelter"}"#).error_message(),
+            Some("Invalid shelter".to_string())
+        );
+        assert_eq!(
+            parse(r#"{"status": "ok", "pets": []}"#).error_message(),
+            None
+        );
+        assert_eq!(
+            parse(r#"{"error": "Invalid API key"}"#).error_message(),
+            Some("Invalid API key".to_string())
+        );
+        assert_eq!(
+            parse(r#"{"error": {"code": 403, "message": "Account suspended"}}"#).error_message(),
+            Some("Account suspended".to_string())
+        );
+        assert_eq!(
+            parse(r#"{"status": "fail", "message": "Unknown shelter"}"#).error_message(),
+            Some("Unknown shelter".to_string())
+        );
+    }
+
+    #[test]
+    fn test_short_description_truncates_on_char_boundary() {
+        let raw = "é".repeat(250);
+        let short = Descriptions::from_html(Some(&raw)).short.unwrap();
+        assert_eq!(short, format!("{}...", "é".repeat(200)));
     }
 
     #[test]
@@ -502,14 +905,14 @@ mod tests {
             sex: Some("Male".to_string()),
             size: Some("Large".to_string()),
             url: "https://example.com/pet/123".to_string(),
+            shelter_id: Some("83349".to_string()),
             photo_url: Some("https://example.com/photo.jpg".to_string()),
-            photos: vec![],
             description: Some("A friendly dog".to_string()),
             description_html: Some("<p>A friendly dog</p>".to_string()),
             description_markdown: Some("A friendly dog".to_string()),
             short_description: Some("A friendly dog".to_string()),
             color: Some("Brown".to_string()),
-            attributes: vec![],
+            ..Default::default()
         };
 
         let json = serde_json::to_value(&pet).expect("Failed to serialize Pet");
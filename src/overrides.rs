@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+
+use crate::models::{card_photo_url, Descriptions, Pet};
+
+/// Staff edits applied on top of every run, keyed by pet ID:
+///
+/// ```toml
+/// [pets.52107063]
+/// breed = "Beagle Mix"            # replace a field
+/// photo_order = [3, 1]            # 1-based, as the source lists them; these first
+/// pin = 1                         # pinned pets lead the list, lowest pin first
+/// expires = 2026-12-31            # ignored from this date on
+/// note = "Breed per vet records"
+///
+/// [pets.52107064]
+/// hidden = true                   # medical hold
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Overrides {
+    #[serde(default)]
+    pub pets: BTreeMap<String, PetOverride>,
+}
+
+/// Edits for a single pet. Unknown keys are rejected so typos don't go unnoticed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PetOverride {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub pet_type: Option<String>,
+    pub breed: Option<String>,
+    pub age: Option<String>,
+    pub sex: Option<String>,
+    pub size: Option<String>,
+    pub url: Option<String>,
+    pub photo_url: Option<String>,
+    /// HTML or plain text; every description format is rebuilt from it
+    pub description: Option<String>,
+    pub color: Option<String>,
+    /// Leave the pet out of the output entirely
+    #[serde(default)]
+    pub hidden: bool,
+    /// 1-based positions in the source's photo list to move to the front, in order
+    pub photo_order: Option<Vec<usize>>,
+    /// Position among pinned pets; pinned pets come before all others
+    pub pin: Option<u32>,
+    /// First day the override no longer applies
+    #[serde(default, deserialize_with = "date")]
+    pub expires: Option<NaiveDate>,
+    /// Free-form reason for the override, for staff only
+    pub note: Option<String>,
+}
+
+/// Accept both TOML dates (2026-12-31) and quoted strings ("2026-12-31").
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDate>, D::Error> {
+    let value = toml::Value::deserialize(deserializer)?;
+    let text = match value {
+        toml::Value::String(text) => text,
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        other => {
+            return Err(serde::de::Error::custom(format!(
+                "expected a date, got {}",
+                other
+            )))
+        }
+    };
+    NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|e| serde::de::Error::custom(format!("invalid date {:?}: {}", text, e)))
+}
+
+impl Overrides {
+    /// Read and parse an overrides file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid overrides file {:?}", path))
+    }
+
+    /// Apply every unexpired override to the pets, as of `today`. Card photos
+    /// picked by `photo_order` are built on `media_base_url`.
+    /// Warns about overrides for pets that are no longer listed.
+    pub fn apply(&self, pets: Vec<Pet>, today: NaiveDate, media_base_url: &str) -> Vec<Pet> {
+        let listed: HashSet<&str> = pets.iter().map(|pet| pet.id.as_str()).collect();
+        for (pet_id, pet_override) in &self.pets {
+            if !listed.contains(pet_id.as_str()) {
+                eprintln!(
+                    "Warning: override for pet {} matches no listed pet{}",
+                    pet_id,
+                    pet_override
+                        .note
+                        .as_deref()
+                        .map(|note| format!(" ({})", note))
+                        .unwrap_or_default()
+                );
+            }
+        }
+
+        let mut hidden = 0;
+        let mut pets: Vec<(Option<u32>, Pet)> = pets
+            .into_iter()
+            .filter_map(|mut pet| {
+                let Some(pet_override) = self.pets.get(&pet.id) else {
+                    return Some((None, pet));
+                };
+                if let Some(expires) = pet_override.expires.filter(|&d| d <= today) {
+                    eprintln!(
+                        "Warning: override for {} ({}) expired on {}; ignoring it",
+                        pet.name, pet.id, expires
+                    );
+                    return Some((None, pet));
+                }
+                if pet_override.hidden {
+                    hidden += 1;
+                    return None;
+                }
+                pet_override.apply_to(&mut pet, media_base_url);
+                Some((pet_override.pin, pet))
+            })
+            .collect();
+
+        if hidden > 0 {
+            println!("Hid {} pets per overrides", hidden);
+        }
+
+        // Pinned pets first, by pin; the stable sort keeps everything else in listing order
+        pets.sort_by_key(|(pin, _)| pin.unwrap_or(u32::MAX));
+        pets.into_iter().map(|(_, pet)| pet).collect()
+    }
+
+    /// IDs of pets whose photos staff arranged (`photo_order` or `photo_url`)
+    /// in an override still in effect on `today`.
+    pub fn fixed_photos(&self, today: NaiveDate) -> HashSet<&str> {
+        self.in_effect(today)
+            .filter(|(_, o)| o.photo_order.is_some() || o.photo_url.is_some())
+            .map(|(pet_id, _)| pet_id.as_str())
+            .collect()
+    }
+
+    /// IDs of pets whose `photoUrl` is set outright by an override still in
+    /// effect on `today`; later steps keep it as written.
+    pub fn fixed_photo_urls(&self, today: NaiveDate) -> HashSet<&str> {
+        self.in_effect(today)
+            .filter(|(_, o)| o.photo_url.is_some())
+            .map(|(pet_id, _)| pet_id.as_str())
+            .collect()
+    }
+
+    fn in_effect(&self, today: NaiveDate) -> impl Iterator<Item = (&String, &PetOverride)> {
+        self.pets
+            .iter()
+            .filter(move |(_, o)| !o.hidden && o.expires.is_none_or(|expires| expires > today))
+    }
+}
+
+impl PetOverride {
+    /// Apply the field replacements and photo order to a pet.
+    fn apply_to(&self, pet: &mut Pet, media_base_url: &str) {
+        let replace = |field: &mut Option<String>, value: &Option<String>| {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        };
+        if let Some(name) = &self.name {
+            pet.name.clone_from(name);
+        }
+        if let Some(pet_type) = &self.pet_type {
+            pet.pet_type.clone_from(pet_type);
+        }
+        if let Some(url) = &self.url {
+            pet.url.clone_from(url);
+        }
+        replace(&mut pet.breed, &self.breed);
+        replace(&mut pet.age, &self.age);
+        replace(&mut pet.sex, &self.sex);
+        replace(&mut pet.size, &self.size);
+        replace(&mut pet.color, &self.color);
+
+        if let Some(description) = &self.description {
+            let descriptions = Descriptions::from_html(Some(description));
+            pet.description = descriptions.text;
+            pet.description_html = descriptions.html;
+            pet.description_markdown = descriptions.markdown;
+            pet.short_description = descriptions.short;
+        }
+
+        if let Some(order) = &self.photo_order {
+            self.reorder_photos(pet, order, media_base_url);
+        }
+        // An explicit photo URL wins over the one derived from the new photo order
+        replace(&mut pet.photo_url, &self.photo_url);
+    }
+
+    /// Move the photos at the given 1-based source positions to the front, in
+    /// order. Positions are counted before failed photos were dropped, so the
+    /// rest still name the photos staff meant.
+    fn reorder_photos(&self, pet: &mut Pet, order: &[usize], media_base_url: &str) {
+        let mut picked = Vec::new();
+        for &position in order {
+            match pet
+                .photos
+                .iter()
+                .position(|photo| position > 0 && photo.source_position == position)
+            {
+                Some(i) if !picked.contains(&i) => picked.push(i),
+                Some(_) => {}
+                None => eprintln!(
+                    "Warning: override for {} ({}) names photo {}, which the source doesn't list \
+                     or whose metadata couldn't be fetched",
+                    pet.name, pet.id, position
+                ),
+            }
+        }
+        if picked.is_empty() {
+            return;
+        }
+
+        let first_before = pet.photos.first().map(|p| p.original_url.clone());
+        let mut photos: Vec<_> = pet.photos.drain(..).map(Some).collect();
+        let mut reordered: Vec<_> = picked.iter().filter_map(|&i| photos[i].take()).collect();
+        reordered.extend(photos.into_iter().flatten());
+        pet.photos = reordered;
+
+        // Keep the card photo in step with the new primary photo
+        let first_after = pet.photos.first().map(|p| p.original_url.as_str());
+        if let Some(url) = first_after.filter(|&url| Some(url) != first_before.as_deref()) {
+            pet.photo_url = Some(card_photo_url(url, media_base_url));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{extract_high_res_image_url, PhotoMetadata, MEDIA_BASE_URL};
+
+    fn pet(id: &str, photo_ids: &[&str]) -> Pet {
+        Pet {
+            id: id.to_string(),
+            name: format!("Pet {}", id),
+            breed: Some("Beagel".to_string()),
+            photos: photo_ids
+                .iter()
+                .zip(1..)
+                .map(|(photo_id, position)| PhotoMetadata {
+                    source_position: position,
+                    ..PhotoMetadata::new(
+                        format!("{}/image/upload/{}", MEDIA_BASE_URL, photo_id),
+                        800,
+                        600,
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let overrides: Overrides = toml::from_str(
+            r#"
+            [pets.1]
+            breed = "Beagle"
+            photo_order = [3, 9]
+
+            [pets.2]
+            hidden = true
+
+            [pets.3]
+            pin = 1
+            description = "<p>Loves naps</p>"
+
+            [pets.4]
+            name = "Renamed"
+            expires = 2026-01-01
+
+            [pets.gone]
+            pin = 2
+            "#,
+        )
+        .unwrap();
+        let pets = vec![
+            pet("1", &["a", "b", "c"]),
+            pet("2", &[]),
+            pet("3", &[]),
+            pet("4", &[]),
+        ];
+
+        let today = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+        let pets = overrides.apply(pets, today, MEDIA_BASE_URL);
+
+        let ids: Vec<_> = pets.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["3", "1", "4"]);
+        assert_eq!(pets[0].description.as_deref(), Some("Loves naps"));
+        assert_eq!(pets[1].breed.as_deref(), Some("Beagle"));
+        let photo_ids: Vec<_> = pets[1]
+            .photos
+            .iter()
+            .map(|p| p.original_url.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(photo_ids, ["c", "a", "b"]);
+        assert_eq!(
+            pets[1].photo_url.as_deref(),
+            extract_high_res_image_url(Some(&pets[1].photos[0].original_url), MEDIA_BASE_URL)
+                .as_deref()
+        );
+        assert_eq!(pets[2].name, "Pet 4");
+        assert_eq!(overrides.fixed_photos(today), HashSet::from(["1"]));
+        assert!(overrides.fixed_photo_urls(today).is_empty());
+    }
+
+    #[test]
+    fn test_photo_order_counts_photos_that_failed() {
+        let overrides: Overrides = toml::from_str("[pets.1]\nphoto_order = [2, 4]").unwrap();
+        // The source listed a, b, c, d; b's metadata failed
+        let mut listed = pet("1", &["a", "b", "c", "d"]);
+        listed.photos.remove(1);
+
+        let today = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+        let pets = overrides.apply(vec![listed], today, MEDIA_BASE_URL);
+
+        let photo_ids: Vec<_> = pets[0]
+            .photos
+            .iter()
+            .map(|p| p.original_url.rsplit('/').next().unwrap())
+            .collect();
+        assert_eq!(photo_ids, ["d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_overrides_reject_unknown_keys_and_bad_dates() {
+        assert!(toml::from_str::<Overrides>("[pets.1]\nbreeed = \"Beagle\"").is_err());
+        assert!(toml::from_str::<Overrides>("[pets.1]\nexpires = \"soon\"").is_err());
+        let overrides: Overrides = toml::from_str("[pets.1]\nexpires = \"2026-12-31\"").unwrap();
+        assert_eq!(
+            overrides.pets["1"].expires,
+            NaiveDate::from_ymd_opt(2026, 12, 31)
+        );
+    }
+}
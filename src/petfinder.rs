@@ -0,0 +1,599 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use tokio::sync::Mutex;
+
+use crate::api::Result;
+use crate::error::ApiError;
+use crate::limits::{ConcurrencyLimits, Host};
+use crate::models::{
+    capitalize_first, combine_breeds, AttributeFlags, Descriptions, Pet, PhotoMetadata,
+};
+use crate::probe::probe_dimensions;
+use crate::redact::ApiKey;
+use crate::retry::RetryPolicy;
+use crate::source::{Listing, PetSource};
+
+/// Default Petfinder v2 API base URL.
+pub const DEFAULT_PETFINDER_BASE_URL: &str = "https://api.petfinder.com/v2";
+
+/// Animals requested per page (Petfinder's maximum).
+const PAGE_SIZE: u32 = 100;
+
+/// Refresh the access token this long before Petfinder says it expires.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Response from the Petfinder OAuth token endpoint.
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Response from the Petfinder /animals endpoint.
+#[derive(Debug, Deserialize)]
+pub struct AnimalsResponse {
+    #[serde(default)]
+    pub animals: Vec<PetfinderAnimal>,
+    pub pagination: Option<Pagination>,
+}
+
+/// Paging info from the /animals endpoint.
+#[derive(Debug, Deserialize)]
+pub struct Pagination {
+    pub current_page: u32,
+    pub total_pages: u32,
+}
+
+/// Animal data from the Petfinder /animals endpoint.
+#[derive(Debug, Deserialize)]
+pub struct PetfinderAnimal {
+    #[serde(deserialize_with = "id_as_string")]
+    pub id: String,
+    pub organization_id: Option<String>,
+    pub url: Option<String>,
+    #[serde(rename = "type")]
+    pub animal_type: Option<String>,
+    pub breeds: Option<PetfinderBreeds>,
+    pub colors: Option<PetfinderColors>,
+    pub age: Option<String>,
+    pub gender: Option<String>,
+    pub size: Option<String>,
+    pub attributes: Option<PetfinderAttributes>,
+    pub environment: Option<PetfinderEnvironment>,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub photos: Vec<PetfinderPhoto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PetfinderBreeds {
+    pub primary: Option<String>,
+    pub secondary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PetfinderColors {
+    pub primary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PetfinderAttributes {
+    pub spayed_neutered: Option<bool>,
+    pub house_trained: Option<bool>,
+    pub declawed: Option<bool>,
+    pub special_needs: Option<bool>,
+    pub shots_current: Option<bool>,
+}
+
+/// Who the animal gets along with. Null means unknown.
+#[derive(Debug, Deserialize)]
+pub struct PetfinderEnvironment {
+    pub children: Option<bool>,
+    pub dogs: Option<bool>,
+    pub cats: Option<bool>,
+}
+
+/// A photo in Petfinder's sizes (medium 300px, large 600px, full); small is unused.
+#[derive(Debug, Deserialize)]
+pub struct PetfinderPhoto {
+    pub medium: Option<String>,
+    pub large: Option<String>,
+    pub full: Option<String>,
+}
+
+/// Petfinder IDs are numbers; we keep every ID as a string.
+fn id_as_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(id) => id,
+        other => other.to_string(),
+    })
+}
+
+struct Token {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Client for the Petfinder v2 API, authenticated with OAuth client credentials.
+pub struct PetfinderApi {
+    client: Client,
+    base_url: String,
+    client_id: String,
+    client_secret: ApiKey,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    token: Mutex<Option<Token>>,
+}
+
+/// Builder for [`PetfinderApi`].
+pub struct PetfinderApiBuilder {
+    client: Option<Client>,
+    base_url: String,
+    client_id: String,
+    client_secret: ApiKey,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+}
+
+impl PetfinderApi {
+    /// Start building an API client.
+    pub fn builder() -> PetfinderApiBuilder {
+        PetfinderApiBuilder {
+            client: None,
+            base_url: DEFAULT_PETFINDER_BASE_URL.to_string(),
+            client_id: String::new(),
+            client_secret: ApiKey::default(),
+            retry: RetryPolicy::default(),
+            limits: ConcurrencyLimits::default(),
+        }
+    }
+
+    /// Fetch every adoptable animal at an organization, paging until the last page.
+    pub async fn get_animals(&self, organization: &str) -> Result<Vec<PetfinderAnimal>> {
+        let mut animals = Vec::new();
+        let mut seen = HashSet::new();
+        let mut page = 1;
+
+        loop {
+            let page_param = page.to_string();
+            let limit = PAGE_SIZE.to_string();
+            let response: AnimalsResponse = self
+                .get_json("animals", |token| {
+                    self.client
+                        .get(format!("{}/animals", self.base_url))
+                        .bearer_auth(token)
+                        .query(&[
+                            ("organization", organization),
+                            ("status", "adoptable"),
+                            ("limit", &limit),
+                            ("page", &page_param),
+                        ])
+                })
+                .await?;
+
+            let received = response.animals.len();
+            animals.extend(
+                response
+                    .animals
+                    .into_iter()
+                    .filter(|animal| seen.insert(animal.id.clone())),
+            );
+
+            let last_page = response
+                .pagination
+                .is_none_or(|p| p.current_page >= p.total_pages);
+            if received == 0 || last_page {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(animals)
+    }
+
+    /// Read each photo's dimensions by probing the full-size image.
+    pub async fn get_photo_metadata(&self, animal: &PetfinderAnimal) -> Vec<Result<PhotoMetadata>> {
+        let futures = animal
+            .photos
+            .iter()
+            .filter_map(|photo| photo.full.as_deref().or(photo.large.as_deref()))
+            .map(|url| async move {
+                let _permit = self.limits.acquire(Host::Media).await;
+                let (width, height) =
+                    probe_dimensions(&self.client, &self.retry, url, None).await?;
+                Ok(PhotoMetadata::new(url.to_string(), width, height))
+            });
+
+        join_all(futures).await
+    }
+
+    /// Send an authenticated request and decode its JSON body.
+    /// A 401 means the token was revoked early, so it is refreshed and the request retried once.
+    async fn get_json<T, F>(&self, endpoint: &str, build: F) -> Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let token = self.token(false).await?;
+        let result = self.send_json(endpoint, || build(&token)).await;
+
+        match result {
+            Err(ApiError::HttpStatus { status, .. }) if status == StatusCode::UNAUTHORIZED => {
+                let token = self.token(true).await?;
+                self.send_json(endpoint, || build(&token)).await
+            }
+            result => result,
+        }
+    }
+
+    async fn send_json<T, F>(&self, endpoint: &str, build: F) -> Result<T>
+    where
+        T: DeserializeOwned,
+        F: Fn() -> RequestBuilder,
+    {
+        let _permit = self.limits.acquire(Host::Api).await;
+
+        let response = self
+            .retry
+            .send(build)
+            .await
+            .map_err(|e| ApiError::transport(endpoint, e))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ApiError::transport(endpoint, e))?;
+
+        if !status.is_success() {
+            return Err(ApiError::http_status(endpoint, status, &body));
+        }
+
+        serde_json::from_str(&body).map_err(|e| ApiError::decode(endpoint, &body, e))
+    }
+
+    /// A valid access token, exchanging the client credentials for a new one when needed.
+    async fn token(&self, force_refresh: bool) -> Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some(current) = token.as_ref() {
+            if !force_refresh && current.expires_at > Instant::now() {
+                return Ok(current.access_token.clone());
+            }
+        }
+
+        let response: TokenResponse = self
+            .send_json("oauth2/token", || {
+                self.client
+                    .post(format!("{}/oauth2/token", self.base_url))
+                    .form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", self.client_id.as_str()),
+                        ("client_secret", self.client_secret.expose()),
+                    ])
+            })
+            .await?;
+
+        let lifetime = Duration::from_secs(response.expires_in).saturating_sub(TOKEN_EXPIRY_MARGIN);
+        *token = Some(Token {
+            access_token: response.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(response.access_token)
+    }
+}
+
+impl PetfinderApiBuilder {
+    /// Base URL of the Petfinder v2 API.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// OAuth client ID (Petfinder's "API key").
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// OAuth client secret.
+    pub fn client_secret(mut self, client_secret: impl Into<ApiKey>) -> Self {
+        self.client_secret = client_secret.into();
+        self
+    }
+
+    /// HTTP client to send requests with.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Retry policy applied to every request.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Concurrency limits applied to every request.
+    pub fn concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn build(self) -> PetfinderApi {
+        PetfinderApi {
+            client: self.client.unwrap_or_default(),
+            base_url: self.base_url,
+            client_id: self.client_id,
+            client_secret: self.client_secret,
+            retry: self.retry,
+            limits: self.limits,
+            token: Mutex::new(None),
+        }
+    }
+}
+
+impl PetfinderAnimal {
+    /// Convert a Petfinder animal + probed photo metadata to our simplified model.
+    pub fn into_pet(self, photos: Vec<PhotoMetadata>) -> Pet {
+        // The 600px "large" rendition is the closest match to our card size
+        let photo_url = self
+            .photos
+            .first()
+            .and_then(|p| p.large.clone().or(p.full.clone()).or(p.medium.clone()));
+
+        let breed = self
+            .breeds
+            .as_ref()
+            .and_then(|b| combine_breeds(b.primary.as_deref(), b.secondary.as_deref()));
+
+        let sex = self
+            .gender
+            .filter(|g| !g.is_empty() && !g.eq_ignore_ascii_case("unknown"));
+
+        let attributes = AttributeFlags {
+            good_with_cats: self.environment.as_ref().and_then(|e| e.cats),
+            good_with_dogs: self.environment.as_ref().and_then(|e| e.dogs),
+            good_with_kids: self.environment.as_ref().and_then(|e| e.children),
+            housetrained: self.attributes.as_ref().and_then(|a| a.house_trained),
+            shots_current: self.attributes.as_ref().and_then(|a| a.shots_current),
+            spayed_neutered: self.attributes.as_ref().and_then(|a| a.spayed_neutered),
+            special_needs: self.attributes.as_ref().and_then(|a| a.special_needs),
+            declawed: self.attributes.as_ref().and_then(|a| a.declawed),
+        }
+        .to_attributes();
+
+        let descriptions = Descriptions::from_html(self.description.as_deref());
+
+        let url = self
+            .url
+            .unwrap_or_else(|| format!("https://www.petfinder.com/petdetail/{}", self.id));
+
+        Pet {
+            id: self.id,
+            name: self.name,
+            pet_type: self
+                .animal_type
+                .as_deref()
+                .map(capitalize_first)
+                .unwrap_or_else(|| "Other".to_string()),
+            breed,
+            age: self.age.as_deref().map(capitalize_first),
+            sex,
+            size: self.size,
+            url,
+            shelter_id: self.organization_id,
+            photo_url,
+            photo_blurhash: None,
+            photo_lqip: None,
+            photo_dominant_color: None,
+            thumbnail_url: None,
+            hero_url: None,
+            social_url: None,
+            photos,
+            description: descriptions.text,
+            description_html: descriptions.html,
+            description_markdown: descriptions.markdown,
+            short_description: descriptions.short,
+            color: self.colors.and_then(|c| c.primary),
+            attributes,
+            littermates: Vec::new(),
+        }
+    }
+}
+
+/// Petfinder as a [`PetSource`]: every adoptable animal at one or more organizations.
+/// The /animals listing already carries full details, so there is no details request.
+pub struct PetfinderSource {
+    api: PetfinderApi,
+    organizations: Vec<String>,
+}
+
+impl PetfinderSource {
+    pub fn new(api: PetfinderApi, organizations: Vec<String>) -> Self {
+        Self { api, organizations }
+    }
+}
+
+impl Listing for PetfinderAnimal {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PetSource for PetfinderSource {
+    type Listing = PetfinderAnimal;
+    type Details = ();
+
+    fn name(&self) -> &str {
+        "Petfinder"
+    }
+
+    async fn list_pets(&self) -> Result<Vec<PetfinderAnimal>> {
+        let mut animals = Vec::new();
+        let mut seen = HashSet::new();
+        for organization in &self.organizations {
+            let listing = self.api.get_animals(organization).await?;
+            println!(
+                "Fetched {} animals from organization {}",
+                listing.len(),
+                organization
+            );
+            animals.extend(
+                listing
+                    .into_iter()
+                    .filter(|animal| seen.insert(animal.id.clone())),
+            );
+        }
+        Ok(animals)
+    }
+
+    async fn fetch_details(&self, _listing: &PetfinderAnimal) -> Result<()> {
+        Ok(())
+    }
+
+    async fn resolve_photos(
+        &self,
+        listing: &PetfinderAnimal,
+        _details: Option<&()>,
+    ) -> Vec<Result<PhotoMetadata>> {
+        self.api.get_photo_metadata(listing).await
+    }
+
+    fn convert(
+        &self,
+        listing: PetfinderAnimal,
+        _details: Option<()>,
+        photos: Vec<PhotoMetadata>,
+    ) -> Pet {
+        listing.into_pet(photos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::png_header;
+    use crate::source::collect_pets;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn animal(id: u64, server: &MockServer) -> serde_json::Value {
+        json!({
+            "id": id,
+            "organization_id": "NJ333",
+            "url": format!("https://www.petfinder.com/dog/{}", id),
+            "type": "Dog",
+            "breeds": { "primary": "Beagle", "secondary": null, "mixed": false },
+            "colors": { "primary": "Tricolor" },
+            "age": "Young",
+            "gender": "Female",
+            "size": "Medium",
+            "attributes": {
+                "spayed_neutered": true,
+                "house_trained": true,
+                "declawed": null,
+                "special_needs": false,
+                "shots_current": true
+            },
+            "environment": { "children": true, "dogs": null, "cats": false },
+            "name": format!("Pet {}", id),
+            "description": "Loves walks &amp; naps",
+            "photos": [{
+                "small": format!("{}/photos/{}/small.jpg", server.uri(), id),
+                "large": format!("{}/photos/{}/large.jpg", server.uri(), id),
+                "full": format!("{}/photos/{}/full.png", server.uri(), id)
+            }]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_petfinder_source_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/oauth2/token"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .and(body_string_contains("client_secret=shh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "expires_in": 3600,
+                "access_token": "token-1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        for page in [1, 2] {
+            Mock::given(method("GET"))
+                .and(path("/v2/animals"))
+                .and(header("Authorization", "Bearer token-1"))
+                .and(query_param("organization", "NJ333"))
+                .and(query_param("page", page.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "animals": [animal(page, &server)],
+                    "pagination": { "current_page": page, "total_pages": 2 }
+                })))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/photos/1/full.png"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(png_header(600, 400)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/photos/2/full.png"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let api = PetfinderApi::builder()
+            .base_url(format!("{}/v2", server.uri()))
+            .client_id("id")
+            .client_secret("shh")
+            .build();
+        let source = PetfinderSource::new(api, vec!["NJ333".to_string()]);
+
+        let listings = source.list_pets().await.unwrap();
+        assert_eq!(listings.len(), 2);
+
+        let mut failures = Vec::new();
+        let pets = collect_pets(&source, listings, &mut failures).await;
+
+        let pet = &pets[0];
+        assert_eq!(pet.id, "1");
+        assert_eq!(pet.breed.as_deref(), Some("Beagle"));
+        assert_eq!(pet.shelter_id.as_deref(), Some("NJ333"));
+        assert_eq!(pet.description.as_deref(), Some("Loves walks & naps"));
+        assert!(pet
+            .photo_url
+            .as_deref()
+            .unwrap()
+            .ends_with("/photos/1/large.jpg"));
+        assert_eq!((pet.photos[0].width, pet.photos[0].height), (600, 400));
+        let keys: Vec<_> = pet.attributes.iter().map(|a| a.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "good_with_kids",
+                "housetrained",
+                "shots_current",
+                "spayed_neutered"
+            ]
+        );
+
+        assert!(pets[1].photos.is_empty());
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].stage, "photo");
+    }
+}
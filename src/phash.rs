@@ -0,0 +1,86 @@
+use std::f64::consts::PI;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Side of the grayscale image the DCT runs on.
+const DCT_SIZE: u32 = 32;
+
+/// Side of the block of low frequencies that make up the hash (8x8 = 64 bits).
+const HASH_SIZE: usize = 8;
+
+/// Perceptual hash (pHash) of an image as 16 hex digits. Resizing, recompression
+/// and small edits barely change it, so near-identical photos hash close together.
+pub fn phash(image: &DynamicImage) -> String {
+    let gray = image
+        .resize_exact(DCT_SIZE, DCT_SIZE, FilterType::Triangle)
+        .to_luma8();
+
+    // cos((2x + 1) u pi / 2N) for the low frequencies u we keep
+    let cosines: Vec<Vec<f64>> = (0..HASH_SIZE)
+        .map(|u| {
+            (0..DCT_SIZE)
+                .map(|x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * DCT_SIZE) as f64).cos())
+                .collect()
+        })
+        .collect();
+
+    let mut coefficients = Vec::with_capacity(HASH_SIZE * HASH_SIZE);
+    for v in 0..HASH_SIZE {
+        for u in 0..HASH_SIZE {
+            let sum: f64 = gray
+                .enumerate_pixels()
+                .map(|(x, y, pixel)| {
+                    pixel.0[0] as f64 * cosines[u][x as usize] * cosines[v][y as usize]
+                })
+                .sum();
+            coefficients.push(sum);
+        }
+    }
+
+    // Compare against the median, leaving out the DC term (overall brightness)
+    let mut ac = coefficients[1..].to_vec();
+    ac.sort_by(f64::total_cmp);
+    let median = ac[ac.len() / 2];
+
+    let hash = coefficients
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | (c > median) as u64);
+    format!("{:016x}", hash)
+}
+
+/// Number of differing bits between two hashes, or None if either isn't a hash.
+pub fn distance(a: &str, b: &str) -> Option<u32> {
+    let a = u64::from_str_radix(a, 16).ok()?;
+    let b = u64::from_str_radix(b, 16).ok()?;
+    Some((a ^ b).count_ones())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// Soft blobs of light and shade, drawn at any size.
+    fn scene(width: u32, height: u32, brighten: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let (u, v) = (x as f64 / width as f64, y as f64 / height as f64);
+            let shade =
+                128.0 + 60.0 * (u * 7.0).sin() * (v * 5.0).cos() + 40.0 * (u * v * 9.0).sin();
+            let value = (shade as u8).saturating_add(brighten);
+            Rgb([value, value, value / 2])
+        }))
+    }
+
+    #[test]
+    fn test_phash_survives_resizing_and_brightness() {
+        let original = phash(&scene(64, 48, 0));
+        let resized = phash(&scene(40, 30, 10));
+        let rotated = phash(&scene(64, 48, 0).rotate90());
+
+        assert_eq!(original.len(), 16);
+        assert!(distance(&original, &resized).unwrap() <= 4);
+        assert!(distance(&original, &rotated).unwrap() > 16);
+        assert_eq!(distance(&original, "not a hash"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use crate::cloudinary::{is_transformation, CloudinaryUrl};
+use crate::models::{build_cloudinary_preset_url, Pet, DEFAULT_CARD_PRESET, MEDIA_BASE_URL};
+
+/// Cloudinary transformations for each image size the site uses, so a design
+/// change only needs a config change:
+///
+/// ```toml
+/// card = "c_fill,w_800,h_600,g_auto/f_auto,q_auto"        # photoUrl
+/// thumbnail = "c_fill,w_200,h_200,g_auto/f_auto,q_auto"   # thumbnailUrl
+/// hero = "c_fill,w_1600,h_900,g_auto/f_auto,q_auto"       # heroUrl
+/// social = "c_fill,w_1200,h_630,g_auto/f_auto,q_auto"     # socialUrl
+/// ```
+///
+/// Presets left out of the file keep their defaults (shown above).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Presets {
+    pub card: String,
+    pub thumbnail: String,
+    pub hero: String,
+    pub social: String,
+    /// Host the URLs are built on; set from the command line, not the file
+    #[serde(skip)]
+    pub media_base_url: String,
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        Self {
+            card: DEFAULT_CARD_PRESET.to_string(),
+            thumbnail: "c_fill,w_200,h_200,g_auto/f_auto,q_auto".to_string(),
+            hero: "c_fill,w_1600,h_900,g_auto/f_auto,q_auto".to_string(),
+            social: "c_fill,w_1200,h_630,g_auto/f_auto,q_auto".to_string(),
+            media_base_url: MEDIA_BASE_URL.to_string(),
+        }
+    }
+}
+
+impl Presets {
+    /// Read and parse a presets file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+        let presets: Self = toml::from_str(&contents)
+            .with_context(|| format!("Invalid presets file {:?}", path))?;
+        presets
+            .validate()
+            .with_context(|| format!("Invalid presets file {:?}", path))?;
+        Ok(presets)
+    }
+
+    /// Reject transformations that would produce a broken URL.
+    fn validate(&self) -> anyhow::Result<()> {
+        let presets = [
+            ("card", &self.card),
+            ("thumbnail", &self.thumbnail),
+            ("hero", &self.hero),
+            ("social", &self.social),
+        ];
+        for (name, transformation) in presets {
+            if !transformation.split('/').all(is_transformation) {
+                bail!(
+                    "{} preset {:?} is not a transformation",
+                    name,
+                    transformation
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Build every preset URL from the pet's card photo. Photos that aren't on
+    /// Adoptapet's Cloudinary (other sources, mirrored photos) keep their URL
+    /// and get no other sizes. With `keep_photo_url` (set by staff), only the
+    /// other sizes are built.
+    pub fn apply(&self, pet: &mut Pet, keep_photo_url: bool) {
+        let image = pet
+            .photo_url
+            .as_deref()
+            .and_then(|url| CloudinaryUrl::parse_on(url, &self.media_base_url).ok());
+        let Some(image) = image else {
+            pet.thumbnail_url = None;
+            pet.hero_url = None;
+            pet.social_url = None;
+            return;
+        };
+        let url = |transformation: &str| {
+            build_cloudinary_preset_url(&image, transformation, &self.media_base_url)
+        };
+        if !keep_photo_url {
+            pet.photo_url = Some(url(&self.card));
+        }
+        pet.thumbnail_url = Some(url(&self.thumbnail));
+        pet.hero_url = Some(url(&self.hero));
+        pet.social_url = Some(url(&self.social));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_fill_in_defaults_and_reject_bad_transformations() {
+        let presets: Presets = toml::from_str(r#"hero = "c_fill,w_2000,h_800""#).unwrap();
+        assert_eq!(presets.hero, "c_fill,w_2000,h_800");
+        assert_eq!(presets.card, DEFAULT_CARD_PRESET);
+        assert!(presets.validate().is_ok());
+
+        assert!(toml::from_str::<Presets>(r#"banner = "w_10""#).is_err());
+        let presets: Presets = toml::from_str(r#"social = "/w_1200/""#).unwrap();
+        assert!(presets.validate().is_err());
+        let presets: Presets = toml::from_str(r#"card = "c_fill,width_800""#).unwrap();
+        assert!(presets.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_a_photo_url_set_by_staff() {
+        let presets = Presets {
+            media_base_url: "https://img.example.org".to_string(),
+            ..Default::default()
+        };
+        let photo_url = format!("{}/image/upload/e_sharpen/1268757503", MEDIA_BASE_URL);
+        let mut pet = Pet {
+            photo_url: Some(photo_url.clone()),
+            ..Default::default()
+        };
+
+        presets.apply(&mut pet, true);
+
+        assert_eq!(pet.photo_url, Some(photo_url));
+        assert_eq!(
+            pet.thumbnail_url.as_deref(),
+            Some("https://img.example.org/image/upload/c_fill,w_200,h_200,g_auto/f_auto,q_auto/1268757503")
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{Rgb, RgbImage};
+
+use crate::color::{palette, PALETTE_SIZE};
+use crate::phash::phash;
+
+/// BlurHash components along x and y; 4x3 matches our 4:3 cards.
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Size of the placeholder rendered from a BlurHash, matching our 4:3 cards.
+const LQIP_SIZE: (u32, u32) = (32, 24);
+
+/// JPEG quality of the rendered placeholder; it is a blur either way.
+const LQIP_QUALITY: u8 = 60;
+
+/// Alphabet of the base 83 encoding BlurHash uses.
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[\\]^_{|}~";
+
+/// What we learn from a tiny rendition of a photo: a placeholder the site
+/// shows while the photo loads, plus a perceptual hash for spotting duplicates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    /// BlurHash of the photo, which [`lqip`] renders as an image
+    pub blurhash: String,
+    /// Most common colors, most common first, as "#rrggbb"
+    pub palette: Vec<String>,
+    /// Perceptual hash, as 16 hex digits
+    pub phash: String,
+}
+
+/// Analyze the bytes of a tiny rendition of a photo.
+pub fn analyze_preview(bytes: &[u8]) -> Result<Preview, image::ImageError> {
+    let format = image::guess_format(bytes)?;
+    let decoded = image::load_from_memory_with_format(bytes, format)?;
+    let image = decoded.to_rgb8();
+    let (x_components, y_components) = BLURHASH_COMPONENTS;
+
+    Ok(Preview {
+        blurhash: blurhash(&image, x_components, y_components),
+        palette: palette(&image, PALETTE_SIZE),
+        phash: phash(&decoded),
+    })
+}
+
+/// Encode an image as a BlurHash (https://blurha.sh) with the given number of components.
+pub fn blurhash(image: &RgbImage, x_components: u32, y_components: u32) -> String {
+    let (width, height) = image.dimensions();
+    let mut factors = Vec::with_capacity((x_components * y_components) as usize);
+
+    for j in 0..y_components {
+        for i in 0..x_components {
+            let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut factor = [0.0f64; 3];
+            for (x, y, pixel) in image.enumerate_pixels() {
+                let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+                for (channel, value) in factor.iter_mut().zip(pixel.0) {
+                    *channel += basis * srgb_to_linear(value);
+                }
+            }
+            let scale = normalization / (width as f64 * height as f64);
+            factors.push(factor.map(|channel| channel * scale));
+        }
+    }
+
+    let (dc, ac) = factors.split_first().expect("at least one component");
+    let mut hash = String::new();
+    encode83((x_components - 1) + (y_components - 1) * 9, 1, &mut hash);
+
+    let maximum_value = if ac.is_empty() {
+        encode83(0, 1, &mut hash);
+        1.0
+    } else {
+        let actual_max = ac
+            .iter()
+            .flat_map(|factor| factor.iter())
+            .fold(0.0f64, |max, value| max.max(value.abs()));
+        let quantised_max = ((actual_max * 166.0 - 0.5).floor()).clamp(0.0, 82.0) as u32;
+        encode83(quantised_max, 1, &mut hash);
+        (quantised_max + 1) as f64 / 166.0
+    };
+
+    let dc_value = dc
+        .iter()
+        .fold(0, |value, &channel| (value << 8) + linear_to_srgb(channel));
+    encode83(dc_value, 4, &mut hash);
+
+    for factor in ac {
+        let value = factor.iter().fold(0, |value, &channel| {
+            let quantised = (sign_pow(channel / maximum_value, 0.5) * 9.0 + 9.5)
+                .floor()
+                .clamp(0.0, 18.0) as u32;
+            value * 19 + quantised
+        });
+        encode83(value, 2, &mut hash);
+    }
+
+    hash
+}
+
+/// Render a BlurHash as a small JPEG data: URL, for sites that want an image
+/// placeholder rather than decoding the hash themselves. Derived on every run
+/// so the image store only keeps the hash. None if the hash is malformed.
+pub fn lqip(hash: &str) -> Option<String> {
+    let (width, height) = LQIP_SIZE;
+    let image = decode_blurhash(hash, width, height)?;
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, LQIP_QUALITY)
+        .encode_image(&image)
+        .ok()?;
+    Some(format!("data:image/jpeg;base64,{}", STANDARD.encode(bytes)))
+}
+
+/// Decode a BlurHash into an image of the given size.
+fn decode_blurhash(hash: &str, width: u32, height: u32) -> Option<RgbImage> {
+    let digits: Vec<u32> = hash.bytes().map(decode83).collect::<Option<_>>()?;
+    let size_flag = *digits.first()?;
+    let (x_components, y_components) = (size_flag % 9 + 1, size_flag / 9 + 1);
+    if digits.len() != 6 + 2 * (x_components * y_components - 1) as usize {
+        return None;
+    }
+
+    let maximum_value = (digits[1] + 1) as f64 / 166.0;
+    let dc = digits[2..6]
+        .iter()
+        .fold(0, |value, &digit| value * 83 + digit);
+    let mut colors = vec![[dc >> 16, (dc >> 8) & 255, dc & 255].map(|c| srgb_to_linear(c as u8))];
+    for pair in digits[6..].chunks(2) {
+        let value = pair[0] * 83 + pair[1];
+        colors.push(
+            [value / (19 * 19), (value / 19) % 19, value % 19]
+                .map(|q| sign_pow((q as f64 - 9.0) / 9.0, 2.0) * maximum_value),
+        );
+    }
+
+    Some(RgbImage::from_fn(width, height, |x, y| {
+        let mut pixel = [0.0f64; 3];
+        for j in 0..y_components {
+            for i in 0..x_components {
+                let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                    * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+                let color = colors[(i + j * x_components) as usize];
+                for (channel, value) in pixel.iter_mut().zip(color) {
+                    *channel += basis * value;
+                }
+            }
+        }
+        Rgb(pixel.map(|channel| linear_to_srgb(channel) as u8))
+    }))
+}
+
+fn decode83(byte: u8) -> Option<u32> {
+    BASE83.iter().position(|&b| b == byte).map(|i| i as u32)
+}
+
+fn encode83(value: u32, length: u32, hash: &mut String) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow(length - i)) % 83;
+        hash.push(BASE83[digit as usize] as char);
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    if v <= 0.0031308 {
+        (v * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+fn sign_pow(value: f64, exponent: f64) -> f64 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_blurhash_of_solid_color() {
+        let image = RgbImage::from_pixel(32, 24, Rgb([255, 0, 0]));
+        let hash = blurhash(&image, 4, 3);
+        // Size flag "L" for 4x3, then the maximum AC, then the pure red DC "TI:j"
+        assert_eq!(hash.len(), 28);
+        assert!(hash.starts_with('L'));
+        assert_eq!(&hash[2..6], "TI:j");
+    }
+
+    #[test]
+    fn test_lqip_renders_the_blurhash() {
+        let image = RgbImage::from_pixel(32, 24, Rgb([255, 0, 0]));
+        let hash = blurhash(&image, 4, 3);
+
+        let decoded = decode_blurhash(&hash, 32, 24).unwrap();
+        // The edges fade a little: the encoder's sums leave the AC components
+        // slightly off zero
+        assert_eq!(decoded.get_pixel(16, 12).0, [255, 0, 0]);
+        assert!(decoded.pixels().all(|p| p.0[0] > 200 && p.0[1..] == [0, 0]));
+        assert!(lqip(&hash)
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,/9j/"));
+        assert_eq!(lqip("L00000"), None);
+        assert_eq!(lqip(""), None);
+    }
+
+    #[test]
+    fn test_analyze_preview_of_jpeg() {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(32, 24)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .unwrap();
+
+        let preview = analyze_preview(&bytes).unwrap();
+
+        assert_eq!(preview.blurhash.len(), 28);
+        assert_eq!(preview.palette, ["#000000"]);
+        assert_eq!(preview.phash.len(), 16);
+    }
+}
@@ -0,0 +1,225 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::models::{card_photo_url, Pet, PhotoMetadata};
+
+/// Default smallest photo, in pixels, kept in a pet's gallery.
+pub const DEFAULT_MIN_PHOTO_WIDTH: u32 = 320;
+pub const DEFAULT_MIN_PHOTO_HEIGHT: u32 = 240;
+
+/// Size of the site's pet cards. Photos at least this big fill a card sharply.
+const CARD_WIDTH: u32 = 800;
+const CARD_HEIGHT: u32 = 600;
+
+/// How photos are filtered and the primary photo picked.
+#[derive(Debug, Clone, Copy)]
+pub struct PhotoPolicy {
+    /// Photos narrower than this are dropped
+    pub min_width: u32,
+    /// Photos shorter than this are dropped
+    pub min_height: u32,
+}
+
+/// What `select_primary_photos` changed, for the run summary.
+#[derive(Debug, Default, PartialEq)]
+pub struct SelectionSummary {
+    /// Photos dropped for being too small
+    pub dropped: usize,
+    /// Pets whose primary photo changed
+    pub promoted: usize,
+}
+
+impl SelectionSummary {
+    pub fn is_empty(&self) -> bool {
+        self.dropped == 0 && self.promoted == 0
+    }
+}
+
+/// Drop photos below the minimum resolution and move each pet's best photo to
+/// the front. A pet's photos are never all dropped: if none meets the minimum,
+/// the largest is kept. `shared` lists photos that also appear on other pets;
+/// pets in `fixed` (photos arranged by staff) are left as they are. New card
+/// photos are built on `media_base_url`.
+pub fn select_primary_photos(
+    pets: &mut [Pet],
+    policy: PhotoPolicy,
+    shared: &HashSet<String>,
+    fixed: &HashSet<&str>,
+    media_base_url: &str,
+) -> SelectionSummary {
+    let mut summary = SelectionSummary::default();
+    for pet in pets
+        .iter_mut()
+        .filter(|pet| !fixed.contains(pet.id.as_str()))
+    {
+        let before = pet.photos.len();
+        let first_before = pet.photos.first().map(|p| p.original_url.clone());
+
+        let big_enough =
+            |p: &PhotoMetadata| p.width >= policy.min_width && p.height >= policy.min_height;
+        if pet.photos.iter().any(big_enough) {
+            pet.photos.retain(big_enough);
+        } else if let Some(largest) = (0..before).max_by_key(|&i| pet.photos[i].pixels()) {
+            eprintln!(
+                "Warning: every photo of {} ({}) is below {}x{}; keeping the largest",
+                pet.name, pet.id, policy.min_width, policy.min_height
+            );
+            pet.photos = vec![pet.photos.swap_remove(largest)];
+        }
+        summary.dropped += before - pet.photos.len();
+
+        let best = pet
+            .photos
+            .iter()
+            .enumerate()
+            .min_by(|(i, a), (j, b)| compare(a, b, shared).then(i.cmp(j)))
+            .map(|(i, _)| i);
+        if let Some(best) = best.filter(|&i| i > 0) {
+            let photo = pet.photos.remove(best);
+            pet.photos.insert(0, photo);
+        }
+
+        // Point the card at the new primary photo
+        let first_after = pet.photos.first().map(|p| p.original_url.as_str());
+        if let Some(url) = first_after.filter(|&url| Some(url) != first_before.as_deref()) {
+            pet.photo_url = Some(card_photo_url(url, media_base_url));
+            summary.promoted += 1;
+        }
+    }
+    summary
+}
+
+/// Photos within this much of the card's aspect ratio (a factor of 4/3, so from
+/// square to 16:9) crop acceptably and are kept in listing order.
+const MAX_ASPECT_DISTANCE: f32 = 0.2877; // ln(4/3)
+
+/// Order photos best first: sharp enough for the card, unique to the pet, then
+/// close to the card's aspect ratio (so `c_fill` crops the least).
+fn compare(a: &PhotoMetadata, b: &PhotoMetadata, shared: &HashSet<String>) -> Ordering {
+    let key = |p: &PhotoMetadata| {
+        (
+            p.width < CARD_WIDTH || p.height < CARD_HEIGHT,
+            shared.contains(&p.original_url),
+        )
+    };
+    let crop = |p: &PhotoMetadata| Some(aspect_distance(p)).filter(|&d| d > MAX_ASPECT_DISTANCE);
+    key(a)
+        .cmp(&key(b))
+        .then_with(|| crop(a).partial_cmp(&crop(b)).unwrap_or(Ordering::Equal))
+}
+
+/// How far a photo's aspect ratio is from the card's, symmetric for wider and taller.
+fn aspect_distance(photo: &PhotoMetadata) -> f32 {
+    let card = CARD_WIDTH as f32 / CARD_HEIGHT as f32;
+    (photo.aspect_ratio / card).ln().abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MEDIA_BASE_URL;
+
+    const POLICY: PhotoPolicy = PhotoPolicy {
+        min_width: DEFAULT_MIN_PHOTO_WIDTH,
+        min_height: DEFAULT_MIN_PHOTO_HEIGHT,
+    };
+
+    fn pet(photos: &[(&str, u32, u32)]) -> Pet {
+        Pet {
+            id: "1".to_string(),
+            photo_url: Some("card".to_string()),
+            photos: photos
+                .iter()
+                .map(|&(id, width, height)| {
+                    PhotoMetadata::new(
+                        format!("{}/image/upload/{}", MEDIA_BASE_URL, id),
+                        width,
+                        height,
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(pet: &Pet) -> Vec<&str> {
+        pet.photos
+            .iter()
+            .map(|p| p.original_url.rsplit('/').next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_primary_photos() {
+        let mut pets = vec![
+            // Tiny intake photo, then a tall portrait, a litter photo and a good 3:2 shot
+            pet(&[
+                ("intake", 200, 150),
+                ("portrait", 900, 1600),
+                ("litter", 1200, 900),
+                ("yard", 1500, 1000),
+                ("blurry", 640, 480),
+            ]),
+            // Already fine: nothing moves
+            pet(&[("good", 1024, 768), ("close", 1200, 900)]),
+        ];
+        let shared = HashSet::from([format!("{}/image/upload/litter", MEDIA_BASE_URL)]);
+
+        let summary =
+            select_primary_photos(&mut pets, POLICY, &shared, &HashSet::new(), MEDIA_BASE_URL);
+
+        assert_eq!(
+            summary,
+            SelectionSummary {
+                dropped: 1,
+                promoted: 1
+            }
+        );
+        assert_eq!(ids(&pets[0]), ["yard", "portrait", "litter", "blurry"]);
+        assert_eq!(
+            pets[0].photo_url.as_deref(),
+            Some(card_photo_url(&pets[0].photos[0].original_url, MEDIA_BASE_URL).as_str())
+        );
+        assert_eq!(ids(&pets[1]), ["good", "close"]);
+        assert_eq!(pets[1].photo_url.as_deref(), Some("card"));
+    }
+
+    #[test]
+    fn test_select_primary_photos_keeps_largest_small_photo() {
+        let mut pets = vec![pet(&[("tiny", 100, 80), ("small", 300, 200)])];
+
+        let summary = select_primary_photos(
+            &mut pets,
+            POLICY,
+            &HashSet::new(),
+            &HashSet::new(),
+            MEDIA_BASE_URL,
+        );
+
+        assert_eq!(
+            summary,
+            SelectionSummary {
+                dropped: 1,
+                promoted: 1
+            }
+        );
+        assert_eq!(ids(&pets[0]), ["small"]);
+    }
+
+    #[test]
+    fn test_select_primary_photos_skips_fixed_pets() {
+        let mut pets = vec![pet(&[("intake", 200, 150), ("yard", 1500, 1000)])];
+
+        let summary = select_primary_photos(
+            &mut pets,
+            POLICY,
+            &HashSet::new(),
+            &HashSet::from(["1"]),
+            MEDIA_BASE_URL,
+        );
+
+        assert!(summary.is_empty());
+        assert_eq!(ids(&pets[0]), ["intake", "yard"]);
+        assert_eq!(pets[0].photo_url.as_deref(), Some("card"));
+    }
+}
@@ -0,0 +1,140 @@
+use reqwest::header::RANGE;
+use reqwest::{Client, StatusCode};
+
+use crate::error::ApiError;
+use crate::fixtures::Fixtures;
+use crate::retry::RetryPolicy;
+
+/// Bytes downloaded when probing an image; enough to cover the header of
+/// JPEG (including typical EXIF blocks), PNG, WebP and AVIF files.
+pub const PROBE_BYTES: usize = 64 * 1024;
+
+/// Endpoint name used in errors from probing.
+const ENDPOINT: &str = "image_probe";
+
+/// Read an image's width and height by downloading only the start of the file.
+/// With `fixtures`, the download is recorded or replayed like any other exchange.
+pub async fn probe_dimensions(
+    client: &Client,
+    retry: &RetryPolicy,
+    url: &str,
+    fixtures: Option<&Fixtures>,
+) -> Result<(u32, u32), ApiError> {
+    let download = || download_head(client, retry, url);
+    let (status, bytes) = match fixtures {
+        Some(fixtures) => fixtures.bytes(ENDPOINT, url, download).await?,
+        None => download().await?,
+    };
+    if !status.is_success() {
+        return Err(ApiError::http_status(ENDPOINT, status, ""));
+    }
+
+    dimensions_from_header(&bytes)
+}
+
+/// Download the start of a file. Asks for a byte range, but also copes with
+/// servers that ignore it and send the whole file, by reading no further than
+/// needed. Error statuses come back with an empty body.
+async fn download_head(
+    client: &Client,
+    retry: &RetryPolicy,
+    url: &str,
+) -> Result<(StatusCode, Vec<u8>), ApiError> {
+    let mut response = retry
+        .send(|| {
+            client
+                .get(url)
+                .header(RANGE, format!("bytes=0-{}", PROBE_BYTES - 1))
+        })
+        .await
+        .map_err(|e| ApiError::transport(ENDPOINT, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Ok((status, Vec::new()));
+    }
+
+    let mut bytes = Vec::new();
+    while bytes.len() < PROBE_BYTES {
+        match response
+            .chunk()
+            .await
+            .map_err(|e| ApiError::transport(ENDPOINT, e))?
+        {
+            Some(chunk) => bytes.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    Ok((status, bytes))
+}
+
+/// Decode width and height from the first bytes of an image file.
+pub fn dimensions_from_header(bytes: &[u8]) -> Result<(u32, u32), ApiError> {
+    let size = imagesize::blob_size(bytes)
+        .map_err(|e| ApiError::missing(ENDPOINT, format!("image dimensions ({})", e)))?;
+    Ok((size.width as u32, size.height as u32))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// The first bytes of a PNG file: signature plus IHDR chunk.
+    pub fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_dimensions_from_png_header() {
+        assert_eq!(
+            dimensions_from_header(&png_header(750, 1000)).unwrap(),
+            (750, 1000)
+        );
+    }
+
+    #[test]
+    fn test_dimensions_from_garbage_is_missing_data() {
+        let error = dimensions_from_header(b"<html>not an image</html>").unwrap_err();
+        assert_eq!(error.kind(), "missing_data");
+    }
+
+    #[tokio::test]
+    async fn test_probe_is_recorded_and_replayed() {
+        use crate::fixtures::FixtureMode;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let dir = std::env::temp_dir().join(format!("update-pets-probe-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/dog.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(png_header(640, 480)))
+            .mount(&server)
+            .await;
+        let url = format!("{}/dog.png", server.uri());
+        let client = Client::new();
+        let retry = RetryPolicy::default();
+
+        let recorder = Fixtures::new(&dir, FixtureMode::Record);
+        let recorded = probe_dimensions(&client, &retry, &url, Some(&recorder)).await;
+        assert_eq!(recorded.unwrap(), (640, 480));
+        drop(server);
+
+        let replayer = Fixtures::new(&dir, FixtureMode::Replay);
+        let replayed = probe_dimensions(&client, &retry, &url, Some(&replayer)).await;
+        assert_eq!(replayed.unwrap(), (640, 480));
+        let missing = format!("{}/cat.png", url.trim_end_matches("/dog.png"));
+        let error = probe_dimensions(&client, &retry, &missing, Some(&replayer))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), "not_recorded");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use reqwest::header::{CONTENT_TYPE, RANGE};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::limits::{ConcurrencyLimits, Host};
+use crate::models::{card_photo_url, Pet, RunFailure};
+use crate::presets::Presets;
+use crate::retry::RetryPolicy;
+
+/// Default hours a photo URL that answered is trusted without checking it again.
+pub const DEFAULT_PHOTO_CHECK_TTL_HOURS: u64 = 24;
+
+/// Default share of photo URLs allowed to fail their check before the run is
+/// stopped; beyond that the CDN or our network is more likely at fault than
+/// the photos.
+pub const DEFAULT_MAX_FAILED_PHOTO_SHARE: f64 = 0.2;
+
+/// Endpoint name used in errors from checking photos.
+const ENDPOINT: &str = "photo_check";
+
+/// Header Cloudinary sets when it serves an error or placeholder instead of the image.
+const CLOUDINARY_ERROR: &str = "x-cld-error";
+
+/// The cache as written to disk: when each reachable URL was last checked.
+/// Broken URLs aren't cached, so a re-upload is noticed on the next run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckCache {
+    reachable: BTreeMap<String, DateTime<Utc>>,
+}
+
+/// HEAD-checks every photo URL in the output, dropping photos that are
+/// definitely gone and replacing a broken card photo with the next photo that loads.
+pub struct PhotoChecker {
+    client: Client,
+    retry: RetryPolicy,
+    limits: ConcurrencyLimits,
+    /// How long a reachable URL is trusted
+    ttl: Duration,
+    /// Share of URLs (0 to 1) allowed to fail before nothing is published
+    max_failed_share: f64,
+    /// Where the cache is kept between runs, if anywhere
+    cache_path: Option<PathBuf>,
+    reachable: Mutex<BTreeMap<String, DateTime<Utc>>>,
+}
+
+impl PhotoChecker {
+    pub fn new(
+        client: Client,
+        retry: RetryPolicy,
+        limits: ConcurrencyLimits,
+        cache_path: Option<PathBuf>,
+        ttl: Duration,
+        max_failed_share: f64,
+    ) -> Self {
+        let reachable = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<CheckCache>(&contents).ok())
+            .map(|cache| cache.reachable)
+            .unwrap_or_default();
+
+        Self {
+            client,
+            retry,
+            limits,
+            ttl,
+            max_failed_share,
+            cache_path,
+            reachable: Mutex::new(reachable),
+        }
+    }
+
+    /// Check each pet's `photoUrl` and photos. Broken photos are dropped, and a
+    /// broken `photoUrl` is rebuilt from the first photo that loads (or cleared).
+    /// Every broken URL is reported. Photos that couldn't be checked (timeouts,
+    /// server errors) are kept with a warning.
+    ///
+    /// Local (mirrored) paths aren't checked, and neither are `thumbnailUrl`,
+    /// `heroUrl` and `socialUrl`: they are the card photo with another
+    /// transformation, rebuilt from whichever card photo passes.
+    ///
+    /// Fails without touching any pet when more than the allowed share of URLs
+    /// fail, so an outage can't strip every photo from the site.
+    pub async fn check_pets(
+        &self,
+        pets: &mut [Pet],
+        presets: &Presets,
+    ) -> anyhow::Result<Vec<RunFailure>> {
+        let urls: BTreeSet<&str> = pets
+            .iter()
+            .flat_map(|pet| {
+                pet.photo_url
+                    .as_deref()
+                    .into_iter()
+                    .chain(pet.photos.iter().map(|p| p.original_url.as_str()))
+            })
+            .filter(|url| is_remote(url))
+            .collect();
+        println!("Checking {} photo URLs...", urls.len());
+
+        let mut results: HashMap<String, Result<(), ApiError>> = join_all(
+            urls.into_iter()
+                .map(|url| async move { (url.to_string(), self.check(url).await) }),
+        )
+        .await
+        .into_iter()
+        .collect();
+
+        let failed = results.values().filter(|result| result.is_err()).count();
+        if failed as f64 > results.len() as f64 * self.max_failed_share {
+            self.save();
+            bail!(
+                "{} of {} photo URLs failed their check; not writing output",
+                failed,
+                results.len()
+            );
+        }
+
+        let mut failures = Vec::new();
+        for pet in pets.iter_mut() {
+            let failure = |pet: &Pet, e: &ApiError| RunFailure {
+                stage: ENDPOINT.to_string(),
+                pet_id: pet.id.clone(),
+                pet_name: pet.name.clone(),
+                kind: e.kind().to_string(),
+                message: e.to_string(),
+            };
+
+            let mut kept = Vec::with_capacity(pet.photos.len());
+            for photo in std::mem::take(&mut pet.photos) {
+                match results.get(&photo.original_url) {
+                    Some(Err(e)) if is_broken(e) => failures.push(failure(pet, e)),
+                    Some(Err(e)) => {
+                        warn_unchecked(pet, &photo.original_url, e);
+                        kept.push(photo);
+                    }
+                    _ => kept.push(photo),
+                }
+            }
+            pet.photos = kept;
+
+            let Some(Err(e)) = pet.photo_url.as_ref().and_then(|url| results.get(url)) else {
+                continue;
+            };
+            if !is_broken(e) {
+                warn_unchecked(pet, pet.photo_url.as_deref().unwrap_or_default(), e);
+                continue;
+            }
+            failures.push(failure(pet, e));
+
+            // Fall back to the first photo whose card URL loads
+            pet.photo_url = None;
+            for index in 0..pet.photos.len() {
+                pet.photo_url = Some(card_photo_url(
+                    &pet.photos[index].original_url,
+                    &presets.media_base_url,
+                ));
+                pet.sync_primary_placeholders(&presets.media_base_url);
+                presets.apply(pet, false);
+                let url = pet.photo_url.clone().unwrap_or_default();
+                if !is_remote(&url) {
+                    break;
+                }
+                let result = match results.remove(&url) {
+                    Some(result) => result,
+                    None => self.check(&url).await,
+                };
+                let ok = !result.as_ref().is_err_and(is_broken);
+                results.insert(url, result);
+                if ok {
+                    let photo = pet.photos.remove(index);
+                    pet.photos.insert(0, photo);
+                    break;
+                }
+                pet.photo_url = None;
+            }
+            if pet.photo_url.is_none() {
+                presets.apply(pet, false);
+                pet.sync_primary_placeholders(&presets.media_base_url);
+            }
+        }
+
+        self.save();
+        Ok(failures)
+    }
+
+    /// Whether a URL serves an image, trusting recent successes.
+    async fn check(&self, url: &str) -> Result<(), ApiError> {
+        let checked_at = self.reachable.lock().unwrap().get(url).copied();
+        if checked_at.is_some_and(|at| self.is_fresh(at)) {
+            return Ok(());
+        }
+
+        let _permit = self.limits.acquire(Host::Media).await;
+        let mut response = self
+            .retry
+            .send(|| self.client.head(url))
+            .await
+            .map_err(|e| ApiError::transport(ENDPOINT, e))?;
+        // Some servers don't do HEAD; ask for a single byte instead
+        if matches!(
+            response.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            response = self
+                .retry
+                .send(|| self.client.get(url).header(RANGE, "bytes=0-0"))
+                .await
+                .map_err(|e| ApiError::transport(ENDPOINT, e))?;
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApiError::http_status(ENDPOINT, status, ""));
+        }
+        if let Some(error) = response.headers().get(CLOUDINARY_ERROR) {
+            let error = error.to_str().unwrap_or_default();
+            return Err(ApiError::missing(ENDPOINT, format!("image ({})", error)));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !content_type.starts_with("image/") {
+            return Err(ApiError::missing(
+                ENDPOINT,
+                format!("image (got {:?})", content_type),
+            ));
+        }
+
+        self.reachable
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), Utc::now());
+        Ok(())
+    }
+
+    fn is_fresh(&self, checked_at: DateTime<Utc>) -> bool {
+        (Utc::now() - checked_at)
+            .to_std()
+            .is_ok_and(|age| age < self.ttl)
+    }
+
+    /// Write the cache back to disk, leaving out entries past their TTL.
+    fn save(&self) {
+        let Some(path) = &self.cache_path else {
+            return;
+        };
+        let cache = CheckCache {
+            reachable: self
+                .reachable
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, &at)| self.is_fresh(at))
+                .map(|(url, &at)| (url.clone(), at))
+                .collect(),
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                fs::write(
+                    path,
+                    serde_json::to_string_pretty(&cache).unwrap_or_default(),
+                )
+            });
+        if let Err(e) = result {
+            eprintln!(
+                "Warning: could not save photo check cache {:?}: {}",
+                path, e
+            );
+        }
+    }
+}
+
+/// Whether a failed check shows the photo is gone, rather than that it
+/// couldn't be checked: a 404 or 410, a Cloudinary error placeholder, or
+/// something other than an image.
+fn is_broken(error: &ApiError) -> bool {
+    match error {
+        ApiError::HttpStatus { status, .. } => {
+            matches!(*status, StatusCode::NOT_FOUND | StatusCode::GONE)
+        }
+        ApiError::MissingData { .. } => true,
+        _ => false,
+    }
+}
+
+fn warn_unchecked(pet: &Pet, url: &str, error: &ApiError) {
+    eprintln!(
+        "Warning: could not check photo {} of {} ({}), keeping it: {}",
+        url, pet.name, pet.id, error
+    );
+}
+
+/// Whether a URL can be checked over HTTP; mirrored photos have site-relative paths.
+fn is_remote(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PhotoMetadata;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn pet(photo_urls: &[String]) -> Pet {
+        Pet {
+            id: "1".to_string(),
+            name: "Biscuit".to_string(),
+            photo_url: photo_urls.first().cloned(),
+            photos: photo_urls
+                .iter()
+                .map(|url| PhotoMetadata::new(url.clone(), 800, 600))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_pets_drops_broken_photos_and_caches_good_ones() {
+        let server = MockServer::start().await;
+        let image = || ResponseTemplate::new(200).insert_header("content-type", "image/jpeg");
+        Mock::given(method("HEAD"))
+            .and(path("/ok.jpg"))
+            .respond_with(image())
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/placeholder.jpg"))
+            .respond_with(image().insert_header(CLOUDINARY_ERROR, "Resource not found"))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/gone.jpg"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/busy.jpg"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let cache_path = std::env::temp_dir().join(format!(
+            "update-pets-photo-check-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&cache_path);
+        let checker = |max_failed_share| {
+            PhotoChecker::new(
+                Client::new(),
+                RetryPolicy {
+                    max_attempts: 1,
+                    ..Default::default()
+                },
+                ConcurrencyLimits::default(),
+                Some(cache_path.clone()),
+                Duration::from_secs(3600),
+                max_failed_share,
+            )
+        };
+        let url = |name: &str| format!("{}/{}", server.uri(), name);
+
+        let urls = [
+            url("gone.jpg"),
+            url("placeholder.jpg"),
+            url("ok.jpg"),
+            url("busy.jpg"),
+        ];
+
+        // Too many failures for the allowed share: nothing is touched
+        let mut pets = vec![pet(&urls)];
+        assert!(checker(0.5)
+            .check_pets(&mut pets, &Presets::default())
+            .await
+            .is_err());
+        assert_eq!(pets[0].photos.len(), 4);
+
+        let mut pets = vec![pet(&urls)];
+        let failures = checker(1.0)
+            .check_pets(&mut pets, &Presets::default())
+            .await
+            .unwrap();
+
+        // The 503 couldn't be checked, so that photo stays
+        let kinds: Vec<_> = failures.iter().map(|f| f.kind.as_str()).collect();
+        assert_eq!(kinds, ["http_status", "missing_data", "http_status"]);
+        let kept: Vec<_> = pets[0].photos.iter().map(|p| &p.original_url).collect();
+        assert_eq!(kept, [&url("ok.jpg"), &url("busy.jpg")]);
+        assert_eq!(pets[0].photo_url, Some(url("ok.jpg")));
+
+        // The second run answers from the cache; the mock expects a single HEAD
+        let mut pets = vec![pet(&[url("ok.jpg")])];
+        assert!(checker(0.0)
+            .check_pets(&mut pets, &Presets::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        fs::remove_file(&cache_path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use reqwest::Url;
+
+/// Placeholder that replaces the API key wherever it would otherwise be shown.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The Adoptapet API key. Debug and Display never print the key itself.
+#[derive(Clone, Default)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// The raw key, for building requests only.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Replace every occurrence of the key in `text`.
+    pub fn redact(&self, text: &str) -> String {
+        if self.0.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(&self.0, REDACTED)
+        }
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        ApiKey(key)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(key: &str) -> Self {
+        ApiKey(key.to_string())
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// The URL with the `key` query parameter masked, for error messages and logs.
+pub fn redact_url(url: &Url) -> Url {
+    rewrite_key(url, Some(REDACTED))
+}
+
+/// The URL with the `key` query parameter removed, for anything written to disk.
+pub fn strip_api_key(url: &Url) -> String {
+    rewrite_key(url, None).to_string()
+}
+
+/// Mask the API key in the URL that reqwest attaches to its errors.
+pub fn redact_error(error: reqwest::Error) -> reqwest::Error {
+    match error.url().map(redact_url) {
+        Some(url) => error.with_url(url),
+        None => error,
+    }
+}
+
+fn rewrite_key(url: &Url, replacement: Option<&str>) -> Url {
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter_map(|(name, value)| match (name.as_ref(), replacement) {
+            ("key", Some(replacement)) => Some((name.into_owned(), replacement.to_string())),
+            ("key", None) => None,
+            _ => Some((name.into_owned(), value.into_owned())),
+        })
+        .collect();
+
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_strip_url() {
+        let url =
+            Url::parse("https://api.example.com/search/pet_details?key=secret&pet_id=1").unwrap();
+        assert_eq!(
+            redact_url(&url).as_str(),
+            "https://api.example.com/search/pet_details?key=%5BREDACTED%5D&pet_id=1"
+        );
+        assert_eq!(
+            strip_api_key(&url),
+            "https://api.example.com/search/pet_details?pet_id=1"
+        );
+
+        let url = Url::parse("https://media.example.com/image/upload/fl_getinfo/1").unwrap();
+        assert_eq!(strip_api_key(&url), url.as_str());
+    }
+
+    #[test]
+    fn test_api_key_never_formats() {
+        let key = ApiKey::from("secret");
+        assert_eq!(format!("{:?} {}", key, key), "[REDACTED] [REDACTED]");
+        assert_eq!(key.redact("key=secret&x=1"), "key=[REDACTED]&x=1");
+        assert_eq!(key.expose(), "secret");
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+/// Default number of attempts per request (including the first).
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+/// Default delay before the first retry, in milliseconds.
+pub const DEFAULT_BASE_DELAY_MS: u64 = 500;
+/// Default upper bound on any single retry delay, in milliseconds.
+pub const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+
+/// Retry policy shared by every Adoptapet and Cloudinary request.
+/// Retries transport errors, 429s and 5xx responses with jittered exponential backoff,
+/// honoring the server's Retry-After header when present.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send the request built by `build`, retrying transient failures.
+    /// Returns the last response or error once attempts are exhausted.
+    pub async fn send<F>(&self, build: F) -> reqwest::Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            let result = build().send().await;
+            if attempt >= max_attempts {
+                return result;
+            }
+
+            let delay = match &result {
+                Ok(response) if is_retryable_status(response.status()) => {
+                    retry_after(response).unwrap_or_else(|| self.backoff_delay(attempt))
+                }
+                Ok(_) => return result,
+                Err(e) if is_retryable_error(e) => self.backoff_delay(attempt),
+                Err(_) => return result,
+            };
+            let delay = delay.min(self.max_delay);
+
+            eprintln!(
+                "Retrying {} in {}ms (attempt {} of {}): {}",
+                describe_url(&result),
+                delay.as_millis(),
+                attempt + 1,
+                max_attempts,
+                describe_failure(&result)
+            );
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Jittered exponential backoff for the given (1-based) failed attempt.
+    /// Picks a delay between half and all of `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let ceiling_ms = ceiling.as_millis() as u64;
+        if ceiling_ms == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::rng().random_range(ceiling_ms / 2..=ceiling_ms))
+    }
+}
+
+/// 429 Too Many Requests and any 5xx are worth retrying.
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Timeouts and connection failures are worth retrying; builder or decode errors are not.
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_request()
+}
+
+/// Read the Retry-After header from a response.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// Parse a Retry-After value, either delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - now;
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+fn describe_url(result: &reqwest::Result<Response>) -> String {
+    let url = match result {
+        Ok(response) => Some(response.url()),
+        Err(e) => e.url(),
+    };
+    url.map(|u| format!("{}{}", u.host_str().unwrap_or_default(), u.path()))
+        .unwrap_or_else(|| "request".to_string())
+}
+
+fn describe_failure(result: &reqwest::Result<Response>) -> String {
+    match result {
+        Ok(response) => format!("HTTP {}", response.status()),
+        Err(e) if e.is_timeout() => "timed out".to_string(),
+        Err(e) if e.is_connect() => "connection failed".to_string(),
+        Err(_) => "request failed".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay_grows_and_caps() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+
+        let first = policy.backoff_delay(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        let second = policy.backoff_delay(2);
+        assert!(second >= Duration::from_millis(100) && second <= Duration::from_millis(200));
+
+        for attempt in 3..10 {
+            let delay = policy.backoff_delay(attempt);
+            assert!(delay >= Duration::from_millis(150) && delay <= Duration::from_millis(300));
+        }
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_retryable_status(StatusCode::OK));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
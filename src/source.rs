@@ -0,0 +1,209 @@
+use futures::future::join_all;
+
+use crate::api::Result;
+use crate::error::ApiError;
+use crate::models::{Pet, PhotoMetadata, RunFailure};
+
+/// A pet as it appears in a source's listing, before details are fetched.
+pub trait Listing {
+    /// The source's ID for the pet.
+    fn id(&self) -> &str;
+    /// The pet's name, for logs and the failure summary.
+    fn name(&self) -> &str;
+}
+
+/// A provider of adoptable pets (Adoptapet, ...) that can feed the pipeline.
+/// The pipeline lists pets, fetches each pet's details, resolves its photos,
+/// then converts everything into our output `Pet`.
+pub trait PetSource {
+    type Listing: Listing;
+    type Details;
+
+    /// Human-readable name for logs, e.g. "Adoptapet".
+    fn name(&self) -> &str;
+
+    /// Fetch every pet currently listed.
+    async fn list_pets(&self) -> Result<Vec<Self::Listing>>;
+
+    /// Fetch the full details for a listed pet.
+    async fn fetch_details(&self, listing: &Self::Listing) -> Result<Self::Details>;
+
+    /// Resolve metadata for each of a pet's photos, one result per photo.
+    async fn resolve_photos(
+        &self,
+        listing: &Self::Listing,
+        details: Option<&Self::Details>,
+    ) -> Vec<Result<PhotoMetadata>>;
+
+    /// Convert a listed pet, its details (if they could be fetched) and its photos into our model.
+    fn convert(
+        &self,
+        listing: Self::Listing,
+        details: Option<Self::Details>,
+        photos: Vec<PhotoMetadata>,
+    ) -> Pet;
+}
+
+/// Fetch details and photos for every listed pet and convert them to `Pet`s.
+/// Pets whose details fail still ship with their listing data, and photos whose
+/// metadata fails are dropped; every such failure is appended to `failures`.
+/// Kept photos remember their position in the source's list.
+pub async fn collect_pets<S: PetSource>(
+    source: &S,
+    listings: Vec<S::Listing>,
+    failures: &mut Vec<RunFailure>,
+) -> Vec<Pet> {
+    // Fetch details for each pet in parallel to get high-res images
+    println!("Fetching pet details from {}...", source.name());
+    let detail_futures: Vec<_> = listings
+        .into_iter()
+        .map(|listing| async move {
+            let details = source.fetch_details(&listing).await;
+            (listing, details)
+        })
+        .collect();
+
+    let pets_with_details: Vec<_> = join_all(detail_futures)
+        .await
+        .into_iter()
+        .map(|(listing, details)| match details {
+            Ok(details) => (listing, Some(details)),
+            Err(e) => {
+                failures.push(run_failure("details", &listing, &e));
+                (listing, None)
+            }
+        })
+        .collect();
+
+    // Fetch metadata for all photos of each pet in parallel
+    println!("Fetching photo metadata...");
+    let metadata_futures: Vec<_> = pets_with_details
+        .into_iter()
+        .map(|(listing, details)| async move {
+            let photos = source.resolve_photos(&listing, details.as_ref()).await;
+            (listing, details, photos)
+        })
+        .collect();
+
+    let pets_with_metadata = join_all(metadata_futures).await;
+
+    // Convert to our output format, dropping photos whose metadata failed
+    pets_with_metadata
+        .into_iter()
+        .map(|(listing, details, photo_results)| {
+            let photos = photo_results
+                .into_iter()
+                .zip(1..)
+                .filter_map(|(result, position)| {
+                    result
+                        .map(|photo| PhotoMetadata {
+                            source_position: position,
+                            ..photo
+                        })
+                        .map_err(|e| failures.push(run_failure("photo", &listing, &e)))
+                        .ok()
+                })
+                .collect();
+            source.convert(listing, details, photos)
+        })
+        .collect()
+}
+
+/// Build a failure summary entry for a listed pet.
+fn run_failure(stage: &str, listing: &impl Listing, error: &ApiError) -> RunFailure {
+    RunFailure {
+        stage: stage.to_string(),
+        pet_id: listing.id().to_string(),
+        pet_name: listing.name().to_string(),
+        kind: error.kind().to_string(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source serving canned pets, for exercising the pipeline without HTTP.
+    struct StubSource;
+
+    struct StubListing(&'static str);
+
+    impl Listing for StubListing {
+        fn id(&self) -> &str {
+            self.0
+        }
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    impl PetSource for StubSource {
+        type Listing = StubListing;
+        type Details = String;
+
+        fn name(&self) -> &str {
+            "Stub"
+        }
+
+        async fn list_pets(&self) -> Result<Vec<StubListing>> {
+            Ok(vec![StubListing("1"), StubListing("2")])
+        }
+
+        async fn fetch_details(&self, listing: &StubListing) -> Result<String> {
+            match listing.0 {
+                "1" => Ok("Friendly".to_string()),
+                _ => Err(ApiError::missing("details", "pet")),
+            }
+        }
+
+        async fn resolve_photos(
+            &self,
+            _listing: &StubListing,
+            details: Option<&String>,
+        ) -> Vec<Result<PhotoMetadata>> {
+            let photo = PhotoMetadata::new("https://example.com/1.jpg".to_string(), 4, 3);
+            match details {
+                Some(_) => vec![Err(ApiError::missing("photo", "width")), Ok(photo)],
+                None => vec![],
+            }
+        }
+
+        fn convert(
+            &self,
+            listing: StubListing,
+            details: Option<String>,
+            photos: Vec<PhotoMetadata>,
+        ) -> Pet {
+            Pet {
+                id: listing.0.to_string(),
+                name: listing.0.to_string(),
+                photos,
+                description: details,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_pets_records_failures_and_keeps_pets() {
+        let source = StubSource;
+        let listings = source.list_pets().await.unwrap();
+        let mut failures = Vec::new();
+
+        let pets = collect_pets(&source, listings, &mut failures).await;
+
+        assert_eq!(pets.len(), 2);
+        assert_eq!(pets[0].description.as_deref(), Some("Friendly"));
+        assert_eq!(pets[0].photos.len(), 1);
+        assert_eq!(pets[0].photos[0].source_position, 2);
+        assert!(pets[1].description.is_none());
+
+        let stages: Vec<_> = failures
+            .iter()
+            .map(|f| (f.stage.as_str(), f.pet_id.as_str()))
+            .collect();
+        assert_eq!(stages, [("details", "2"), ("photo", "1")]);
+    }
+}